@@ -0,0 +1,14 @@
+use std::{env, process::Command};
+
+fn main() {
+    // Record the version of the compiler, so that plugins and the root
+    // loading them can check that they were built by the same compiler:
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let output = Command::new(rustc).arg("-vV").output()
+        .expect("Failed to run `rustc -vV`.");
+    let version = String::from_utf8(output.stdout)
+        .expect("The output of `rustc -vV` is not valid UTF-8.");
+    let version = version.lines().next().unwrap_or_default();
+    println!("cargo:rustc-env=NXS_RUSTC_VERSION={}", version);
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,62 @@
+//! The interface through which leaf modules read their configuration.
+
+use futures::stream::BoxStream;
+use serde::de::DeserializeOwned;
+
+use crate::{self as nxs, root::{Interface, LeafModule}};
+
+pub use toml::Table;
+
+/// A source of configuration, divided into one section for each module.
+///
+/// Each module reads only its own section, which the root chooses according
+/// to the identity of the importing module: that of a named instance is the
+/// section named after the instance. Each section is a TOML table, which a
+/// module normally reads into its own serde type using
+/// [`section`](#method.section), for example:
+/// ```
+/// # use serde::Deserialize;
+/// # use nxs_interface::{root::RootModule, config::ConfigManager};
+/// #
+/// #[derive(Default, Deserialize)]
+/// #[serde(default, deny_unknown_fields)]
+/// struct CommandsConfig { prefix: String }
+///
+/// # async fn example(root: &'static dyn RootModule)
+/// # -> nxs_interface::Result<()> {
+/// let config = root.import::<dyn ConfigManager>().await?;
+/// let settings: CommandsConfig = config.get()?.section()?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait ConfigManager: LeafModule {
+    /// Returns the name of the module whose section is read.
+    fn module(&self) -> &'static str;
+
+    /// Returns the section of the module, which is empty if it has none.
+    fn raw_section(&self) -> Table;
+
+    /// Returns a stream which yields each time the section of the module
+    /// changes, after which the new section is available from
+    /// [`raw_section`](Self::raw_section).
+    fn changes(&self) -> BoxStream<'static, ()>;
+}
+
+impl Interface for dyn ConfigManager {
+    const VERSION: &'static str = "0.2.0";
+}
+
+impl dyn ConfigManager {
+    /// Reads the section of the module into the type `T`.
+    ///
+    /// Fails with [`nxs::Error::Config`], naming the module and the offending
+    /// key, if the section cannot be deserialized into `T`.
+    pub fn section<T: DeserializeOwned>(&self) -> nxs::Result<T> {
+        let section = toml::Value::Table(self.raw_section());
+        serde_path_to_error::deserialize(section).map_err(|error| {
+            let key = error.path().to_string();
+            let key = if key == "." { String::new() } else { key };
+            nxs::Error::config(self.module(), key, error.into_inner().message())
+        })
+    }
+}
@@ -0,0 +1,201 @@
+//! The [`Error`] type shared by all modules.
+
+use std::any::Any;
+use std::error::Error as StdError;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use semver::Version;
+
+use crate::TypeInfo;
+
+/// An error arising from the loading or operation of a module.
+///
+/// Errors are cheaply cloneable, so that the same error can be delivered to
+/// every party interested in, for example, the failure of a single load. The
+/// cause of an error, if any, is available through [`StdError::source`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// No provider is available for the interface `interface`.
+    NotProvided { interface: TypeInfo },
+
+    /// The module named `module` failed to load, because of `source`.
+    LoadFailed { module: &'static str, source: Arc<Error> },
+
+    /// Importing the first interface in `path` would have required it to wait
+    /// for itself to load, as each interface in `path` is waiting for the
+    /// next, and the last is the same as the first.
+    Cycle { path: Vec<TypeInfo> },
+
+    /// The module named `module` provides `interface` at version `provided`,
+    /// which is incompatible with the version `required` by the importer.
+    IncompatibleVersion {
+        interface: TypeInfo, module: &'static str,
+        required: Box<Version>, provided: Box<Version>,
+    },
+
+    /// The module providing `interface` cannot be cast to it, but only to the
+    /// types in `castable`.
+    NotCastable { interface: TypeInfo, castable: Vec<TypeInfo> },
+
+    /// The module providing `interface` was unloaded after it was imported.
+    Unloaded { interface: TypeInfo },
+
+    /// No module named `name` is registered.
+    UnknownModule { name: String },
+
+    /// No named instance `name` of a module providing `interface` is
+    /// available.
+    NoInstance { interface: TypeInfo, name: String },
+
+    /// The configuration of the module named `module` is invalid at `key`, or
+    /// as a whole if `key` is `None`.
+    Config { module: &'static str, key: Option<String>, message: String },
+
+    /// The module named `module` is not allowed to import `interface`.
+    AccessDenied { module: &'static str, interface: TypeInfo },
+
+    /// A module panicked, with the given message, for example while loading.
+    Panicked { message: String },
+
+    /// An operation, such as loading a module, did not finish within the time
+    /// `timeout` allowed for it.
+    TimedOut { timeout: Duration },
+
+    /// An error specific to the module named `module`.
+    Module { module: &'static str, source: Arc<dyn StdError + Send + Sync> },
+
+    /// A task was cancelled, because the module that spawned it was unloaded
+    /// or it was cancelled through its handle.
+    Cancelled,
+
+    /// The root does not support `operation`.
+    Unsupported { operation: &'static str },
+}
+
+impl Error {
+    /// Constructs an [`Error::Module`] from any error or message.
+    pub fn module(
+        module: &'static str, source: impl Into<Box<dyn StdError + Send + Sync>>,
+    ) -> Self {
+        Self::Module { module, source: Arc::from(source.into()) }
+    }
+
+    /// Constructs an [`Error::Config`] for the given key, or for the whole
+    /// configuration if `key` is empty.
+    pub fn config(
+        module: &'static str, key: impl Into<String>, message: impl fmt::Display,
+    ) -> Self {
+        let key = Some(key.into()).filter(|key| !key.is_empty());
+        Self::Config { module, key, message: message.to_string() }
+    }
+
+    /// Constructs an [`Error::Panicked`] from the payload of a caught panic.
+    pub fn panicked(panic: Box<dyn Any + Send>) -> Self {
+        let message = match panic.downcast::<String>() {
+            Ok(message) => *message,
+            Err(panic) => match panic.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "(no message)".to_string(),
+            },
+        };
+        Self::Panicked { message }
+    }
+
+    /// Constructs an [`Error::LoadFailed`] caused by this error.
+    pub fn load_failed(self, module: &'static str) -> Self {
+        Self::LoadFailed { module, source: Arc::new(self) }
+    }
+
+    /// Returns the innermost `Error` in the chain of causes of this error.
+    pub fn root_cause(&self) -> &Error {
+        match self {
+            Self::LoadFailed { source, .. } => source.root_cause(),
+            _                               => self,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotProvided { interface } => {
+                write!(f, "no provider for interface `{}`", interface)
+            }
+            Self::LoadFailed { module, source } => {
+                write!(f, "module `{}` failed to load: {}", module, source)
+            }
+            Self::Cycle { path } => {
+                let path: Vec<String> = path.iter().map(|t| {
+                    t.short_name().trim_start_matches("dyn ").to_string()
+                }).collect();
+                write!(f, "dependency cycle detected: {}", path.join(" -> "))
+            }
+            Self::IncompatibleVersion {
+                interface, module, required, provided,
+            } => {
+                write!(f, "module `{}` provides `{}` at version {}, which is \
+                           incompatible with the required version {}",
+                       module, interface.short_name(), provided, required)
+            }
+            Self::NotCastable { interface, castable } => {
+                let castable: Vec<String> = castable.iter()
+                    .map(TypeInfo::short_name).collect();
+                write!(f, "provider cannot be cast to `{}`, only to: {}",
+                       interface.short_name(), castable.join(", "))
+            }
+            Self::Unloaded { interface } => {
+                write!(f, "the provider of interface `{}` has been unloaded",
+                       interface)
+            }
+            Self::UnknownModule { name } => {
+                write!(f, "no module named `{}`", name)
+            }
+            Self::NoInstance { interface, name } => {
+                write!(f, "no instance named `{}` provides interface `{}`",
+                       name, interface)
+            }
+            Self::Config { module, key: Some(key), message } => {
+                write!(f, "invalid configuration for module `{}` at `{}`: {}",
+                       module, key, message)
+            }
+            Self::Config { module, key: None, message } => {
+                write!(f, "invalid configuration for module `{}`: {}",
+                       module, message)
+            }
+            Self::AccessDenied { module, interface } => {
+                write!(f, "module `{}` is not allowed to import interface `{}`",
+                       module, interface)
+            }
+            Self::Panicked { message } => {
+                write!(f, "a panic occurred: {}", message)
+            }
+            Self::TimedOut { timeout } => {
+                write!(f, "timed out after {:?}", timeout)
+            }
+            Self::Module { module, source } => {
+                write!(f, "module `{}`: {}", module, source)
+            }
+            Self::Cancelled => {
+                write!(f, "the task was cancelled")
+            }
+            Self::Unsupported { operation } => {
+                write!(f, "{} is not supported by this root", operation)
+            }
+        }
+    }
+}
+
+impl StdError for Error {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            Self::LoadFailed { source, .. } => Some(&**source),
+            Self::Module { source, .. }     => Some(&**source),
+            _                               => None,
+        }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
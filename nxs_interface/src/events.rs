@@ -0,0 +1,189 @@
+//! The interface through which leaf modules exchange events without importing
+//! each other.
+
+use std::any::{Any, TypeId};
+use std::ops::Deref;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use futures::stream::{BoxStream, StreamExt};
+
+use crate::{
+    util::dyn_cast::{DynCast, DynCastExt},
+    root::{Interface, LeafModule, RootModule},
+};
+
+/// A bus on which modules publish events of any `'static` type, and subscribe
+/// to events by type.
+///
+/// Each event is delivered to the subscribers to its own type, and, if it was
+/// published with [`publish_castable`](#method.publish_castable), also to the
+/// subscribers to each type to which it can be cast by [`DynCast`], for
+/// example:
+#[cfg_attr(feature = "derive", doc = "```")]
+#[cfg_attr(not(feature = "derive"), doc = "```ignore")]
+/// # use futures::StreamExt;
+/// # use nxs_interface::{
+/// #     util::dyn_cast::DynCast, root::RootModule, events::EventBus,
+/// # };
+/// #
+/// trait ChatEvent: DynCast + Send + Sync { fn text(&self) -> &str; }
+///
+/// #[derive(DynCast)]
+/// #[dyn_cast(base_traits(ChatEvent))]
+/// struct Message { text: String }
+///
+/// impl ChatEvent for Message { fn text(&self) -> &str { &self.text } }
+///
+/// # async fn example(root: &'static dyn RootModule)
+/// # -> nxs_interface::Result<()> {
+/// let bus = root.import::<dyn EventBus>().await?;
+/// let mut chat = bus.get()?.subscribe_castable::<dyn ChatEvent>(root);
+/// bus.get()?.publish_castable(Message { text: "hello".to_string() });
+/// assert_eq!(chat.next().await.unwrap().text(), "hello");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each subscription belongs to the module whose root was given to subscribe,
+/// and is dropped, ending its stream, once that module is unloaded.
+pub trait EventBus: LeafModule {
+    /// Delivers `event` to each current subscriber to it.
+    fn dyn_publish(&self, event: DynEvent);
+
+    /// Returns a stream of the events published after this call which match
+    /// `to`, on behalf of the module to which `root` was given.
+    fn dyn_subscribe(&self, root: &dyn RootModule, to: Subscription)
+    -> BoxStream<'static, DynEvent>;
+}
+
+impl Interface for dyn EventBus {
+    const VERSION: &'static str = "0.1.0";
+}
+
+/// The events to which a subscriber subscribes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Subscription {
+    /// The events of the type with the given ID.
+    Exact(TypeId),
+
+    /// The events published as castable which can be cast to the type with
+    /// the given ID.
+    Castable(TypeId),
+}
+
+/// An event as it is published, of a type known only at runtime.
+#[derive(Clone)]
+pub struct DynEvent {
+    event: Arc<dyn Any + Send + Sync>,
+    castable: Option<Arc<dyn DynCast + Send + Sync>>,
+}
+
+impl DynEvent {
+    /// Constructs an event which is delivered only to subscribers to `E`.
+    pub fn new<E: Any + Send + Sync>(event: E) -> Self {
+        Self { event: Arc::new(event), castable: None }
+    }
+
+    /// Constructs an event which is delivered to subscribers to `E`, and to
+    /// castable subscribers to each type to which `E` can be cast.
+    pub fn castable<E: DynCast + Send + Sync>(event: E) -> Self {
+        let event = Arc::new(event);
+        Self { event: event.clone(), castable: Some(event) }
+    }
+
+    /// Returns the [`TypeId`] of the type of the event.
+    pub fn type_id(&self) -> TypeId {
+        (*self.event).type_id()
+    }
+
+    /// Returns the [`TypeId`]s of the types to which the event can be cast,
+    /// which is empty unless it was constructed by
+    /// [`castable`](Self::castable).
+    pub fn castable_types(&self) -> Vec<TypeId> {
+        self.castable.as_ref().map_or_else(Vec::new, |e| e.castable_types())
+    }
+
+    /// Tells whether the event matches the subscription `to`.
+    pub fn matches(&self, to: Subscription) -> bool {
+        match to {
+            Subscription::Exact(id) => self.type_id() == id,
+            Subscription::Castable(id) => {
+                self.castable.as_ref().is_some_and(|e| e.dyn_can_cast(id))
+            }
+        }
+    }
+
+    /// Returns the event as an `E`, if that is its type.
+    pub fn downcast<E: Any + Send + Sync>(self) -> Option<Arc<E>> {
+        self.event.downcast().ok()
+    }
+
+    /// Returns the event cast to `T`, if it was constructed by
+    /// [`castable`](Self::castable) and can be cast to `T`.
+    pub fn cast_ref<T: Any + ?Sized>(&self) -> Option<&T> {
+        self.castable.as_ref()?.cast_ref()
+    }
+}
+
+/// An event received by a castable subscription, which dereferences to the
+/// type `T` to which it was cast.
+pub struct Event<T: ?Sized + 'static> {
+    event: DynEvent,
+    cast: PhantomData<fn() -> &'static T>,
+}
+
+impl<T: ?Sized + 'static> Event<T> {
+    /// Returns the event as it was published.
+    pub fn as_dyn(&self) -> &DynEvent {
+        &self.event
+    }
+}
+
+impl<T: ?Sized + 'static> Clone for Event<T> {
+    fn clone(&self) -> Self {
+        Self { event: self.event.clone(), cast: PhantomData }
+    }
+}
+
+impl<T: ?Sized + 'static> Deref for Event<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.event.cast_ref().expect("An event must match its subscription.")
+    }
+}
+
+impl dyn EventBus {
+    /// Publishes `event` to the subscribers to its type `E`.
+    pub fn publish<E: Any + Send + Sync>(&self, event: E) {
+        self.dyn_publish(DynEvent::new(event))
+    }
+
+    /// Publishes `event` to the subscribers to its type `E`, and to the
+    /// castable subscribers to each type to which it can be cast.
+    pub fn publish_castable<E: DynCast + Send + Sync>(&self, event: E) {
+        self.dyn_publish(DynEvent::castable(event))
+    }
+
+    /// Subscribes to the events of type `E`, on behalf of the module to which
+    /// `root` was given.
+    pub fn subscribe<E: Any + Send + Sync>(&self, root: &dyn RootModule)
+    -> BoxStream<'static, Arc<E>> {
+        let to = Subscription::Exact(TypeId::of::<E>());
+        self.dyn_subscribe(root, to).filter_map(|event| async move {
+            event.downcast()
+        }).boxed()
+    }
+
+    /// Subscribes to the events published as castable which can be cast to
+    /// `T`, such as `dyn ChatEvent`, on behalf of the module to which `root`
+    /// was given.
+    pub fn subscribe_castable<T: Any + ?Sized>(&self, root: &dyn RootModule)
+    -> BoxStream<'static, Event<T>> {
+        let to = Subscription::Castable(TypeId::of::<T>());
+        self.dyn_subscribe(root, to).filter_map(move |event| async move {
+            event.matches(to).then_some(Event { event, cast: PhantomData })
+        }).boxed()
+    }
+}
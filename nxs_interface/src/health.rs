@@ -0,0 +1,124 @@
+//! The interface through which leaf modules report their health.
+//!
+//! A module reports its health by implementing [`Health`] and listing it
+//! among the base traits to which it may be cast, for example:
+#![cfg_attr(all(feature = "derive", feature = "text"), doc = "```")]
+#![cfg_attr(not(all(feature = "derive", feature = "text")), doc = "```ignore")]
+//! # use nxs_interface::{
+//! #     self as nxs, util::dyn_cast::DynCast,
+//! #     root::{LeafModule, RootModule}, text::TextManager,
+//! #     health::{Health, HealthStatus},
+//! # };
+//! #
+//! #[derive(DynCast, LeafModule)]
+//! #[dyn_cast(base_traits(LeafModule, TextManager, Health))]
+//! #[leaf_module(provides(TextManager))]
+//! pub struct IrcTextManager { /* ... */ }
+//! #
+//! # impl IrcTextManager {
+//! #     async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+//! #         Ok(IrcTextManager {})
+//! #     }
+//! #
+//! #     fn is_connected(&self) -> bool { true }
+//! # }
+//! #
+//! # impl TextManager for IrcTextManager {
+//! #     fn send(&self, _target: &str, _text: &str) -> nxs::Result<()> {
+//! #         Ok(())
+//! #     }
+//! # }
+//!
+//! impl Health for IrcTextManager {
+//!     fn health(&self) -> HealthStatus {
+//!         match self.is_connected() {
+//!             true  => HealthStatus::healthy("connected"),
+//!             false => HealthStatus::failed("disconnected"),
+//!         }
+//!     }
+//! }
+//! ```
+//! The root then finds it through [`health_of`]. Modules which do not
+//! implement it are assumed to be healthy once they have loaded.
+
+use std::fmt;
+
+use crate::{util::dyn_cast::DynCastExt, root::LeafModule};
+
+/// How well a module is working, from the best to the worst.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HealthState {
+    Healthy,
+
+    /// The module is working, but not fully, e.g. it has lost one of several
+    /// connections, or is retrying after an error.
+    Degraded,
+
+    /// The module is not working.
+    Failed,
+}
+
+impl HealthState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Healthy  => "healthy",
+            Self::Degraded => "degraded",
+            Self::Failed   => "failed",
+        }
+    }
+}
+
+impl fmt::Display for HealthState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The health of a module at one time, and a message explaining it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HealthStatus {
+    pub state: HealthState,
+    pub message: String,
+}
+
+impl HealthStatus {
+    pub fn new(state: HealthState, message: impl Into<String>) -> Self {
+        Self { state, message: message.into() }
+    }
+
+    pub fn healthy(message: impl Into<String>) -> Self {
+        Self::new(HealthState::Healthy, message)
+    }
+
+    pub fn degraded(message: impl Into<String>) -> Self {
+        Self::new(HealthState::Degraded, message)
+    }
+
+    pub fn failed(message: impl Into<String>) -> Self {
+        Self::new(HealthState::Failed, message)
+    }
+}
+
+impl fmt::Display for HealthStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.message.is_empty() {
+            true  => write!(f, "{}", self.state),
+            false => write!(f, "{}: {}", self.state, self.message),
+        }
+    }
+}
+
+/// A check of the health of a module, which it may optionally implement.
+///
+/// This is called by the root whenever a status report is requested, so it
+/// should return quickly, reporting the state that the module last observed
+/// rather than, for example, waiting on the network.
+pub trait Health: Sync {
+    fn health(&self) -> HealthStatus;
+}
+
+/// Returns the health of `module`, or `None` if it does not implement
+/// [`Health`].
+pub fn health_of(module: &dyn LeafModule) -> Option<HealthStatus> {
+    module.cast_ref::<dyn Health>().map(Health::health)
+}
@@ -6,8 +6,34 @@ pub mod util;
 #[cfg(feature = "root")]
 pub mod root;
 
+// Part of `root`, as every root gives its leaf modules a logger:
+#[cfg(feature = "root")]
+pub mod logging;
+
+#[cfg(feature = "health")]
+pub mod health;
+
+// Part of `root`, as every root gives its leaf modules a task spawner:
+#[cfg(feature = "root")]
+pub mod task;
+
 #[cfg(feature = "text")]
 pub mod text;
 
-pub type Error = &'static str;
-pub type Result<T> = std::result::Result<T, Error>;
+#[cfg(feature = "events")]
+pub mod events;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+#[cfg(feature = "config")]
+pub mod config;
+
+#[cfg(feature = "plugin")]
+pub mod plugin;
+
+mod error;
+pub use error::{Error, Result};
+
+mod type_info;
+pub use type_info::TypeInfo;
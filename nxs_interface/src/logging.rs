@@ -0,0 +1,241 @@
+//! The interface through which leaf modules write log messages.
+//!
+//! Each leaf module is given a [`Logger`] by its root, through
+//! [`RootModule::logger`](crate::root::RootModule::logger), which tags each
+//! message with the name and version of the module. Messages are normally
+//! written using the macros [`nxs_error!`](crate::nxs_error),
+//! [`nxs_warn!`](crate::nxs_warn), [`nxs_info!`](crate::nxs_info),
+//! [`nxs_debug!`](crate::nxs_debug) and [`nxs_trace!`](crate::nxs_trace),
+//! optionally with structured fields, for example:
+//! ```
+//! # use nxs_interface::{self as nxs, root::RootModule};
+//! #
+//! # fn example(root: &dyn RootModule, commands: &[&str], name: String) {
+//! nxs::nxs_info!(root.logger(), "loaded {} commands", commands.len());
+//! nxs::nxs_warn!(root.logger(), { user: name.as_str(), attempts: 3 },
+//!                "rejected login");
+//! # }
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+/// The severity of a log message, from the most to the least severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warn  => "warn",
+            Self::Info  => "info",
+            Self::Debug => "debug",
+            Self::Trace => "trace",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = String;
+
+    /// Parses the name of a level, ignoring case.
+    fn from_str(name: &str) -> Result<Self, String> {
+        let levels = [
+            Self::Error, Self::Warn, Self::Info, Self::Debug, Self::Trace,
+        ];
+        levels.iter().copied()
+            .find(|level| level.as_str().eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("unrecognised log level `{}`", name))
+    }
+}
+
+/// The value of a structured field of a log message.
+#[derive(Clone, Copy)]
+pub enum Value<'a> {
+    Str(&'a str),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Bool(bool),
+
+    /// A value of any other type, recorded as it is displayed.
+    Display(&'a dyn fmt::Display),
+}
+
+impl fmt::Display for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(value)     => value.fmt(f),
+            Self::Int(value)     => value.fmt(f),
+            Self::UInt(value)    => value.fmt(f),
+            Self::Float(value)   => value.fmt(f),
+            Self::Bool(value)    => value.fmt(f),
+            Self::Display(value) => value.fmt(f),
+        }
+    }
+}
+
+impl fmt::Debug for Value<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Str(value) => write!(f, "{:?}", value),
+            _ => write!(f, "{}", self),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Value<'a> {
+    fn from(value: &'a str) -> Self { Self::Str(value) }
+}
+
+impl<'a> From<&'a String> for Value<'a> {
+    fn from(value: &'a String) -> Self { Self::Str(value) }
+}
+
+impl From<bool> for Value<'_> {
+    fn from(value: bool) -> Self { Self::Bool(value) }
+}
+
+macro_rules! impl_from {
+    ($variant:ident($as:ty): $($type:ty),*) => {$(
+        impl From<$type> for Value<'_> {
+            fn from(value: $type) -> Self { Self::$variant(value as $as) }
+        }
+    )*};
+}
+
+impl_from!(Int(i64): i8, i16, i32, i64, isize);
+impl_from!(UInt(u64): u8, u16, u32, u64, usize);
+impl_from!(Float(f64): f32, f64);
+
+/// A log message, as written by a module.
+#[derive(Clone, Copy, Debug)]
+pub struct Record<'a> {
+    pub level: Level,
+
+    /// The path of the Rust module in which the message was written, e.g.
+    /// `nxs_std_cmds::parser`.
+    pub target: &'a str,
+
+    pub message: fmt::Arguments<'a>,
+
+    /// The structured fields of the message, by name.
+    pub fields: &'a [(&'a str, Value<'a>)],
+}
+
+/// A destination for the log messages of one module, which tags them with
+/// the name and version of that module.
+pub trait Logger: Send + Sync {
+    /// Tells whether messages at `level` are currently written, so that they
+    /// need not be formatted otherwise.
+    fn enabled(&self, level: Level) -> bool;
+
+    /// Writes `record`, if its level is enabled.
+    fn log(&self, record: &Record<'_>);
+}
+
+/// A [`Logger`] which discards every message.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullLogger;
+
+impl Logger for NullLogger {
+    fn enabled(&self, _level: Level) -> bool {
+        false
+    }
+
+    fn log(&self, _record: &Record<'_>) {}
+}
+
+/// Writes a log message to a [`Logger`] at the given [`Level`], with optional
+/// structured fields, formatting the message only if the level is enabled.
+///
+/// ```
+/// # use nxs_interface::{self as nxs, logging::{Level, Logger}};
+/// #
+/// # fn example(logger: &dyn Logger, channel: String) {
+/// nxs::nxs_log!(logger, Level::Info, "joined {}", channel);
+/// nxs::nxs_log!(logger, Level::Info, { channel: channel.as_str() }, "joined");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! nxs_log {
+    (
+        $logger:expr, $level:expr,
+        { $($key:ident: $value:expr),* $(,)? }, $($arg:tt)+
+    ) => {{
+        let logger: &dyn $crate::logging::Logger = $logger;
+        let level: $crate::logging::Level = $level;
+        if logger.enabled(level) {
+            logger.log(&$crate::logging::Record {
+                level,
+                target: ::std::module_path!(),
+                message: ::std::format_args!($($arg)+),
+                fields: &[$((
+                    ::std::stringify!($key),
+                    $crate::logging::Value::from($value),
+                )),*],
+            });
+        }
+    }};
+    ($logger:expr, $level:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $level, {}, $($arg)+)
+    };
+}
+
+/// Writes a log message at [`Level::Error`], as by
+/// [`nxs_log!`](crate::nxs_log).
+#[macro_export]
+macro_rules! nxs_error {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $crate::logging::Level::Error, $($arg)+)
+    };
+}
+
+/// Writes a log message at [`Level::Warn`], as by
+/// [`nxs_log!`](crate::nxs_log).
+#[macro_export]
+macro_rules! nxs_warn {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $crate::logging::Level::Warn, $($arg)+)
+    };
+}
+
+/// Writes a log message at [`Level::Info`], as by
+/// [`nxs_log!`](crate::nxs_log).
+#[macro_export]
+macro_rules! nxs_info {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $crate::logging::Level::Info, $($arg)+)
+    };
+}
+
+/// Writes a log message at [`Level::Debug`], as by
+/// [`nxs_log!`](crate::nxs_log).
+#[macro_export]
+macro_rules! nxs_debug {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $crate::logging::Level::Debug, $($arg)+)
+    };
+}
+
+/// Writes a log message at [`Level::Trace`], as by
+/// [`nxs_log!`](crate::nxs_log).
+#[macro_export]
+macro_rules! nxs_trace {
+    ($logger:expr, $($arg:tt)+) => {
+        $crate::nxs_log!($logger, $crate::logging::Level::Trace, $($arg)+)
+    };
+}
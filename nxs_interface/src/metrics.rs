@@ -0,0 +1,197 @@
+//! The interface through which leaf modules publish metrics.
+//!
+//! A module registers each of its metrics once, normally when it is loaded,
+//! and then updates the returned handle, which is cheap to clone and to
+//! update from any thread, for example:
+//! ```
+//! # use nxs_interface::{root::RootModule, metrics::Metrics};
+//! #
+//! # async fn example(root: &'static dyn RootModule)
+//! # -> nxs_interface::Result<()> {
+//! let metrics = root.import::<dyn Metrics>().await?;
+//! let handled = metrics.get()?.counter(
+//!     "commands_handled_total", "The number of commands handled.")?;
+//! handled.inc();
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::{self as nxs, root::{Interface, LeafModule}};
+
+/// The upper bounds of the buckets of a [`Histogram`] of durations in
+/// seconds, suitable for most purposes.
+pub const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// A registry of metrics, which namespaces the name of each metric by the
+/// name of the module that registers it.
+///
+/// Registering a metric with the name of one already registered by the same
+/// module returns that metric, if it is of the same kind, or fails otherwise.
+/// A name may contain only ASCII letters, digits and underscores.
+pub trait Metrics: LeafModule {
+    /// Registers a counter, which only increases, described by `help`.
+    fn counter(&self, name: &str, help: &str) -> nxs::Result<Counter>;
+
+    /// Registers a gauge, which may increase and decrease, described by
+    /// `help`.
+    fn gauge(&self, name: &str, help: &str) -> nxs::Result<Gauge>;
+
+    /// Registers a histogram with the given bucket upper bounds, described by
+    /// `help`.
+    fn histogram(&self, name: &str, help: &str, buckets: &[f64])
+    -> nxs::Result<Histogram>;
+}
+
+impl Interface for dyn Metrics {
+    const VERSION: &'static str = "0.1.0";
+}
+
+/// Tells whether `name` may be used as the name of a metric.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// A count which only increases.
+#[derive(Clone, Debug, Default)]
+pub struct Counter(Arc<AtomicU64>);
+
+impl Counter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn inc(&self) {
+        self.inc_by(1)
+    }
+
+    pub fn inc_by(&self, n: u64) {
+        self.0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A value which may increase and decrease.
+#[derive(Clone, Debug, Default)]
+pub struct Gauge(Arc<AtomicF64>);
+
+impl Gauge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, value: f64) {
+        self.0.set(value)
+    }
+
+    pub fn add(&self, delta: f64) {
+        self.0.add(delta)
+    }
+
+    pub fn inc(&self) {
+        self.add(1.0)
+    }
+
+    pub fn dec(&self) {
+        self.add(-1.0)
+    }
+
+    pub fn get(&self) -> f64 {
+        self.0.get()
+    }
+}
+
+/// A distribution of observed values, counted in buckets.
+#[derive(Clone, Debug)]
+pub struct Histogram(Arc<HistogramState>);
+
+#[derive(Debug)]
+struct HistogramState {
+    bounds: Vec<f64>,
+
+    /// The number of observations in each bucket, not including those in
+    /// preceding buckets, followed by the number greater than every bound.
+    counts: Vec<AtomicU64>,
+
+    sum: AtomicF64,
+}
+
+/// The state of a [`Histogram`] at one time.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HistogramSnapshot {
+    /// The upper bound of each bucket, and the number of observations less
+    /// than or equal to it.
+    pub buckets: Vec<(f64, u64)>,
+
+    /// The total number of observations.
+    pub count: u64,
+
+    /// The sum of the observed values.
+    pub sum: f64,
+}
+
+impl Histogram {
+    /// Constructs a histogram with the given bucket upper bounds, ignoring any
+    /// that are not finite.
+    pub fn new(buckets: &[f64]) -> Self {
+        let mut bounds: Vec<f64> = buckets.iter().copied()
+            .filter(|bound| bound.is_finite()).collect();
+        bounds.sort_by(f64::total_cmp);
+        bounds.dedup();
+        let counts = (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect();
+        let sum = AtomicF64::default();
+        Self(Arc::new(HistogramState { bounds, counts, sum }))
+    }
+
+    pub fn observe(&self, value: f64) {
+        let bucket = self.0.bounds.iter().position(|&bound| value <= bound)
+            .unwrap_or(self.0.bounds.len());
+        self.0.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.0.sum.add(value);
+    }
+
+    /// Returns the upper bounds of the buckets.
+    pub fn bounds(&self) -> &[f64] {
+        &self.0.bounds
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut count = 0;
+        let mut buckets = Vec::with_capacity(self.0.bounds.len());
+        for (i, n) in self.0.counts.iter().enumerate() {
+            count += n.load(Ordering::Relaxed);
+            if let Some(&bound) = self.0.bounds.get(i) {
+                buckets.push((bound, count));
+            }
+        }
+        HistogramSnapshot { buckets, count, sum: self.0.sum.get() }
+    }
+}
+
+/// An `f64` which may be updated atomically.
+#[derive(Debug, Default)]
+struct AtomicF64(AtomicU64);
+
+impl AtomicF64 {
+    fn get(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn set(&self, value: f64) {
+        self.0.store(value.to_bits(), Ordering::Relaxed)
+    }
+
+    fn add(&self, delta: f64) {
+        let _ = self.0.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |v| {
+            Some((f64::from_bits(v) + delta).to_bits())
+        });
+    }
+}
@@ -0,0 +1,116 @@
+//! Distribution of leaf modules as dynamically loaded plugins.
+//!
+//! A plugin is a `cdylib` crate which declares the leaf modules it contains
+//! using [`export_plugin!`](crate::export_plugin). This exports a
+//! [`PluginDeclaration`] under the symbol [`ENTRY_SYMBOL`], through which a
+//! root may register the modules, after checking that the plugin was built
+//! compatibly with the root itself.
+//!
+//! Since the interfaces between modules are Rust trait objects, whose layout
+//! is not stable, a plugin is only compatible with a root built by exactly
+//! the same version of `rustc`, and against a compatible version of this
+//! crate with the same features enabled.
+//!
+#![cfg_attr(feature = "derive", doc = "```")]
+#![cfg_attr(not(feature = "derive"), doc = "```ignore")]
+//! # use nxs_interface::{
+//! #     self as nxs, util::dyn_cast::DynCast, root::{LeafModule, RootModule},
+//! # };
+//! #
+//! # #[derive(DynCast, LeafModule)]
+//! # #[dyn_cast(base_traits(LeafModule))]
+//! # struct StdTextManager;
+//! #
+//! # impl StdTextManager {
+//! #     async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+//! #         Ok(StdTextManager)
+//! #     }
+//! # }
+//! #
+//! # #[derive(DynCast, LeafModule)]
+//! # #[dyn_cast(base_traits(LeafModule))]
+//! # struct Commands;
+//! #
+//! # impl Commands {
+//! #     async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+//! #         Ok(Commands)
+//! #     }
+//! # }
+//! #
+//! nxs_interface::export_plugin!(StdTextManager, Commands);
+//! ```
+
+use std::os::raw::c_char;
+
+use crate::root::{LeafModule, LoadFn, Manifest};
+
+/// The version of the layout of [`PluginDeclaration`].
+///
+/// This is incremented whenever the layout changes, along with the version
+/// suffix of [`ENTRY_SYMBOL`].
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the symbol under which a plugin exports its declaration.
+pub const ENTRY_SYMBOL: &str = "nxs_plugin_entry_v1";
+
+/// The version of `rustc` with which this crate was built, as reported by the
+/// first line of `rustc -vV`.
+pub const RUSTC_VERSION: &str = env!("NXS_RUSTC_VERSION");
+
+/// The version of this crate.
+pub const INTERFACE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[doc(hidden)]
+pub const RUSTC_VERSION_NUL: &str = concat!(env!("NXS_RUSTC_VERSION"), "\0");
+
+#[doc(hidden)]
+pub const INTERFACE_VERSION_NUL: &str = concat!(env!("CARGO_PKG_VERSION"), "\0");
+
+/// The declaration exported by a plugin under [`ENTRY_SYMBOL`].
+///
+/// The fields up to and including `interface_version` have a stable layout, so
+/// that they may be checked before `register` is trusted. They must point to
+/// NUL-terminated strings.
+#[repr(C)]
+pub struct PluginDeclaration {
+    pub abi_version: u32,
+    pub rustc_version: *const c_char,
+    pub interface_version: *const c_char,
+    pub register: fn(&mut dyn PluginRegistrar),
+}
+
+// The string pointers of a declaration refer to immutable static data.
+unsafe impl Sync for PluginDeclaration {}
+
+/// A receiver of the leaf modules declared by a plugin.
+pub trait PluginRegistrar {
+    /// Registers a leaf module with the given manifest, which is loaded by
+    /// `load`.
+    fn add_module(&mut self, manifest: Manifest, load: LoadFn);
+}
+
+impl dyn PluginRegistrar + '_ {
+    /// Registers the leaf module `M`.
+    pub fn register<M: LeafModule>(&mut self) {
+        self.add_module(M::manifest(), M::dyn_load)
+    }
+}
+
+/// Exports a [`PluginDeclaration`] registering each of the given leaf module
+/// types, making the current `cdylib` crate loadable as a plugin.
+///
+/// This may be invoked at most once in each plugin.
+#[macro_export]
+macro_rules! export_plugin {
+    ($($module:ty),* $(,)?) => {
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static nxs_plugin_entry_v1: $crate::plugin::PluginDeclaration
+        = $crate::plugin::PluginDeclaration {
+            abi_version: $crate::plugin::ABI_VERSION,
+            rustc_version: $crate::plugin::RUSTC_VERSION_NUL.as_ptr() as _,
+            interface_version: $crate::plugin::INTERFACE_VERSION_NUL.as_ptr() as _,
+            register: |_registrar| { $(_registrar.register::<$module>();)* },
+        };
+    };
+}
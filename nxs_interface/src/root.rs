@@ -1,35 +1,168 @@
-use std::any::TypeId;
-
-use crate::{self as nxs, util::dyn_cast::{DynCast, DynCastRef}};
+use crate::{self as nxs, TypeInfo, util::dyn_cast::{DynCast, DynCastRef}};
+use crate::logging::{Logger, NullLogger};
+use crate::task::{Spawner, NullSpawner};
 
 use futures::future::BoxFuture;
 
 pub use root_module::RootModule;
-pub use leaf_module::LeafModule;
+pub use leaf_module::{LeafModule, LoadFn, Manifest, Scope};
+pub use interface::{Interface, InterfaceInfo};
+pub use handle::{Handle, DynHandle, Liveness, Unloaded};
+pub use semver::Version;
 
 pub mod root_module {
     use super::*;
 
     pub trait RootModule: DynCast + Sync {
-        fn dyn_import(&'static self, as_type: TypeId)
-        -> BoxFuture<nxs::Result<DynCastRef<'_>>>;
+        fn dyn_import(&'static self, as_type: InterfaceInfo)
+        -> BoxFuture<'static, nxs::Result<DynHandle>>;
+
+        /// Imports every available provider of `as_type`, in order of
+        /// preference, failing if any of them fails to load, or if there are
+        /// providers but none implements a compatible version.
+        ///
+        /// By default, this yields the single provider given by
+        /// [`dyn_import`](Self::dyn_import), if there is one.
+        fn dyn_import_all(&'static self, as_type: InterfaceInfo)
+        -> BoxFuture<'static, nxs::Result<Vec<DynHandle>>> {
+            Box::pin(async move {
+                match self.dyn_import(as_type).await {
+                    Ok(handle) => Ok(vec![handle]),
+                    Err(nxs::Error::NotProvided { .. }) => Ok(Vec::new()),
+                    Err(error) => Err(error),
+                }
+            })
+        }
+
+        /// Imports `as_type` from the named instance `name` of a module
+        /// providing it, which is distinct from the provider imported by
+        /// [`dyn_import`](Self::dyn_import), and from any other instance.
+        ///
+        /// By default, no instances are available, so this fails with
+        /// [`nxs::Error::NoInstance`].
+        fn dyn_import_named(&'static self, as_type: InterfaceInfo, name: &str)
+        -> BoxFuture<'static, nxs::Result<DynHandle>> {
+            let name = name.to_string();
+            Box::pin(async move {
+                Err(nxs::Error::NoInstance { interface: as_type.type_info, name })
+            })
+        }
+
+        /// Returns the name of the instance to which this root was given when
+        /// it was loaded, if it is a named instance of a leaf module.
+        ///
+        /// Each instance should read its configuration from the section with
+        /// this name, rather than from the section of its module.
+        fn instance(&self) -> Option<&'static str> {
+            None
+        }
+
+        /// Returns the name of the module on whose behalf the leaf module to
+        /// which this root was given is being loaded, if its
+        /// [scope](Scope) is not [`Scope::Singleton`] and it is being loaded
+        /// for another module, rather than for the root itself.
+        fn importer(&self) -> Option<&'static str> {
+            None
+        }
+
+        /// Returns the liveness of the instance to which this root was given
+        /// when it was loaded, which becomes invalid when it is unloaded, or
+        /// `None` if this is the root itself.
+        ///
+        /// A module may use this to release resources held on behalf of
+        /// another module, such as subscriptions, once that module is
+        /// unloaded.
+        fn liveness(&self) -> Option<Liveness> {
+            None
+        }
+
+        /// Returns the logger through which the leaf module to which this root
+        /// was given should write its log messages, tagged with its name and
+        /// version.
+        fn logger(&self) -> &dyn Logger {
+            &NullLogger
+        }
+
+        /// Returns the spawner through which the leaf module to which this
+        /// root was given should run its background tasks, which are
+        /// cancelled when it is unloaded.
+        fn spawner(&self) -> &dyn Spawner {
+            &NullSpawner
+        }
     }
 
     const ROOT_MODULE_ERR: &str =
         "The contract of `RootModule` has been violated by an implementation.";
 
-    pub async fn import_from<M: LeafModule + ?Sized>(
+    pub async fn import_from<M: Interface + ?Sized>(
         root: &'static (impl RootModule + ?Sized)
-    ) -> nxs::Result<&M> {
-        let dyn_ref: DynCastRef = root.dyn_import(TypeId::of::<M>()).await?;
-        Ok(dyn_ref.cast::<M>().expect(ROOT_MODULE_ERR))
+    ) -> nxs::Result<Handle<M>> {
+        let handle = root.dyn_import(InterfaceInfo::of::<M>()).await?;
+        Ok(handle.cast::<M>().unwrap_or_else(|| {
+            panic!("{} (importing `{}`)", ROOT_MODULE_ERR, TypeInfo::of::<M>())
+        }))
+    }
+
+    /// Imports `M` as by [`import_from`], except that if no provider of `M` is
+    /// available, this returns `None` rather than [`nxs::Error::NotProvided`].
+    ///
+    /// Any other error, such as the failure of a provider to load, is still
+    /// returned, even if it was caused by the absence of a provider of some
+    /// other interface.
+    pub async fn try_import_from<M: Interface + ?Sized>(
+        root: &'static (impl RootModule + ?Sized)
+    ) -> nxs::Result<Option<Handle<M>>> {
+        match import_from(root).await {
+            Ok(handle) => Ok(Some(handle)),
+            Err(nxs::Error::NotProvided { .. }) => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    pub async fn import_all_from<M: Interface + ?Sized>(
+        root: &'static (impl RootModule + ?Sized)
+    ) -> nxs::Result<Vec<Handle<M>>> {
+        let handles = root.dyn_import_all(InterfaceInfo::of::<M>()).await?;
+        Ok(handles.into_iter().map(|handle| handle.cast::<M>().unwrap_or_else(|| {
+            panic!("{} (importing `{}`)", ROOT_MODULE_ERR, TypeInfo::of::<M>())
+        })).collect())
+    }
+
+    pub async fn import_named_from<M: Interface + ?Sized>(
+        root: &'static (impl RootModule + ?Sized), name: &str,
+    ) -> nxs::Result<Handle<M>> {
+        let handle = root.dyn_import_named(InterfaceInfo::of::<M>(), name).await?;
+        Ok(handle.cast::<M>().unwrap_or_else(|| {
+            panic!("{} (importing `{}`)", ROOT_MODULE_ERR, TypeInfo::of::<M>())
+        }))
     }
 
     impl dyn RootModule {
-        pub async fn import<M: LeafModule + ?Sized>(&'static self)
-        -> nxs::Result<&M> {
+        pub async fn import<M: Interface + ?Sized>(&'static self)
+        -> nxs::Result<Handle<M>> {
             import_from(self).await
         }
+
+        /// Imports `M` if it is provided, as by [`try_import_from`].
+        pub async fn try_import<M: Interface + ?Sized>(&'static self)
+        -> nxs::Result<Option<Handle<M>>> {
+            try_import_from(self).await
+        }
+
+        /// Imports every available provider of `M`, as by
+        /// [`RootModule::dyn_import_all`].
+        pub async fn import_all<M: Interface + ?Sized>(&'static self)
+        -> nxs::Result<Vec<Handle<M>>> {
+            import_all_from(self).await
+        }
+
+        /// Imports `M` from the named instance `name`, as by
+        /// [`RootModule::dyn_import_named`].
+        pub async fn import_named<M: Interface + ?Sized>(
+            &'static self, name: &str,
+        ) -> nxs::Result<Handle<M>> {
+            import_named_from(self, name).await
+        }
     }
 }
 
@@ -39,7 +172,343 @@ pub mod leaf_module {
 
     pub trait LeafModule: DynCast + Sync {
         fn dyn_load(root: &'static dyn RootModule)
-        -> BoxFuture<nxs::Result<Box<dyn LeafModule>>>
+        -> BoxFuture<'static, nxs::Result<Box<dyn LeafModule>>>
+        where Self: Sized;
+
+        /// Returns the static metadata describing this module.
+        fn manifest() -> Manifest
         where Self: Sized;
+
+        /// Returns the same value as [`manifest`](Self::manifest), given an
+        /// instance of this module.
+        fn dyn_manifest(&self) -> Manifest;
+
+        /// Stops this module, for example by flushing state, closing
+        /// connections and cancelling tasks, before the program exits or the
+        /// module is unloaded.
+        ///
+        /// The root calls this at most once for each instance, after shutting
+        /// down all modules that imported from this one. By default, this
+        /// does nothing. When `LeafModule` is derived with
+        /// `#[leaf_module(shutdown)]`, this calls an inherent method
+        /// `async fn shutdown(&'static self) -> Result<(), E>`, for any error
+        /// type `E` convertible to [`nxs::Error`].
+        fn shutdown(&'static self) -> BoxFuture<'static, nxs::Result<()>> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// A function that loads a leaf module, such as [`LeafModule::dyn_load`].
+    pub type LoadFn = fn(&'static dyn RootModule)
+    -> BoxFuture<'static, nxs::Result<Box<dyn LeafModule>>>;
+
+    /// Static metadata describing a leaf module, available before it is
+    /// loaded.
+    ///
+    /// When `LeafModule` is derived, the manifest is specified by the
+    /// `leaf_module` helper attribute, for example:
+    /// ```text
+    /// #[leaf_module(name = "StdTextManager", version = "1.0.0",
+    ///               provides(TextManager), requires(ConfigManager))]
+    /// ```
+    /// where `name` defaults to the name of the type, `version` defaults to the
+    /// version of the crate, and `provides`, `requires` and `optional` each
+    /// default to an empty list of traits `T` for which `dyn T` implements
+    /// [`Interface`].
+    /// The attribute may also include `shutdown`, as described under
+    /// [`LeafModule::shutdown`], and `scope = "singleton"`, `"per_importer"`
+    /// or `"transient"`, as described under [`Scope`].
+    #[derive(Clone, Debug)]
+    pub struct Manifest {
+        /// The name of the module, which should be unique among all modules.
+        pub name: &'static str,
+
+        /// The version of the module itself.
+        pub version: Version,
+
+        /// The interfaces to which the module can be cast, and for which it
+        /// is intended to be imported, at the versions it implements.
+        pub provides: Vec<InterfaceInfo>,
+
+        /// The interfaces which the module may import, at the versions it
+        /// expects.
+        pub requires: Vec<InterfaceInfo>,
+
+        /// The interfaces which the module imports only if they are provided,
+        /// for example by `try_import`, at the versions it expects.
+        pub optional: Vec<InterfaceInfo>,
+
+        /// How instances of the module are shared between its importers.
+        pub scope: Scope,
+    }
+
+    /// How the instances of a leaf module are shared between the modules
+    /// importing it.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+    pub enum Scope {
+        /// A single instance is shared by all importers.
+        #[default]
+        Singleton,
+
+        /// A separate instance is loaded for each importing module, which may
+        /// identify that module by [`RootModule::importer`].
+        PerImporter,
+
+        /// A new instance is loaded for every import.
+        ///
+        /// The root does not keep track of a transient instance once it has
+        /// loaded, so that repeated imports do not accumulate: it is not
+        /// listed as loaded, it is not shut down, and its handles remain valid
+        /// when its module is unloaded, although the modules that imported it
+        /// are unloaded as usual. Its background tasks run until they finish.
+        /// A transient module should therefore hold no resources that need to
+        /// be released.
+        Transient,
+    }
+}
+
+pub mod interface {
+    use super::*;
+
+    /// A trait object type, such as `dyn TextManager`, which can be imported
+    /// from a [`RootModule`].
+    ///
+    /// Each interface declares a [semantic version][semver], which is recorded
+    /// both by modules providing the interface and by modules importing it,
+    /// at the time they are compiled. An import succeeds only if the version
+    /// implemented by the provider is compatible with the version expected by
+    /// the importer, in the sense of [`InterfaceInfo::is_compatible`].
+    ///
+    /// The version should therefore be updated whenever the interface trait,
+    /// or any type appearing in it, changes: the major version (or the minor
+    /// version, before 1.0.0) for incompatible changes, and otherwise the
+    /// minor or patch version.
+    ///
+    /// # Examples
+    /// ```
+    /// # use nxs_interface::root::{Interface, LeafModule};
+    /// pub trait Greeter: LeafModule {
+    ///     fn greet(&self) -> String;
+    /// }
+    ///
+    /// impl Interface for dyn Greeter {
+    ///     const VERSION: &'static str = "1.0.0";
+    /// }
+    /// ```
+    ///
+    /// [semver]: https://semver.org
+    pub trait Interface: LeafModule {
+        /// The version of this interface, which must be valid semver.
+        const VERSION: &'static str;
+    }
+
+    /// The identity and version of an [`Interface`].
+    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+    pub struct InterfaceInfo {
+        pub type_info: TypeInfo,
+        pub version: Version,
+    }
+
+    const VERSION_ERR: &str = "The version of an `Interface` must be valid semver.";
+
+    impl InterfaceInfo {
+        /// Returns the `InterfaceInfo` of the interface `M`, with the version
+        /// declared by `M` in the current crate.
+        pub fn of<M: Interface + ?Sized>() -> Self {
+            let version = Version::parse(M::VERSION).unwrap_or_else(|_| {
+                panic!("{} (`{}`)", VERSION_ERR, TypeInfo::of::<M>())
+            });
+            Self { type_info: TypeInfo::of::<M>(), version }
+        }
+
+        /// Tells whether a provider implementing this interface at version
+        /// `provided` can be used by an importer expecting version `self`.
+        ///
+        /// This follows the rules for Cargo's default (caret) version
+        /// requirements: `provided` must be at least `self.version`, and
+        /// have the same leftmost nonzero component.
+        pub fn is_compatible(&self, provided: &Version) -> bool {
+            let required = &self.version;
+            let req = semver::Comparator {
+                op: semver::Op::Caret,
+                major: required.major,
+                minor: Some(required.minor),
+                patch: Some(required.patch),
+                pre: required.pre.clone(),
+            };
+            req.matches(provided)
+        }
+    }
+}
+
+pub mod handle {
+    use super::*;
+    use std::any::Any;
+    use std::collections::HashMap;
+    use std::fmt;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::{Arc, Mutex, atomic::{AtomicBool, Ordering}};
+    use std::task::{Context, Poll, Waker};
+
+    /// The liveness of one loaded instance of a leaf module, shared between
+    /// the root and all [`Handle`]s to that instance.
+    ///
+    /// An instance is live from the time it starts loading until the root
+    /// unloads it, after which every handle to it is invalid.
+    #[derive(Clone)]
+    pub struct Liveness(Arc<LivenessState>);
+
+    struct LivenessState {
+        live: AtomicBool,
+        waiting: Mutex<Waiting>,
+    }
+
+    /// The tasks waiting on [`Unloaded`] futures, by the keys of those
+    /// futures.
+    #[derive(Default)]
+    struct Waiting {
+        next_key: usize,
+        wakers: HashMap<usize, Waker>,
+    }
+
+    impl Liveness {
+        pub fn new() -> Self {
+            Self(Arc::new(LivenessState {
+                live: AtomicBool::new(true), waiting: Mutex::default(),
+            }))
+        }
+
+        pub fn is_live(&self) -> bool {
+            self.0.live.load(Ordering::Acquire)
+        }
+
+        /// Marks the instance as unloaded, invalidating all handles to it.
+        pub fn invalidate(&self) {
+            self.0.live.store(false, Ordering::Release);
+            let wakers = {
+                let mut waiting = self.0.waiting.lock().unwrap();
+                std::mem::take(&mut waiting.wakers)
+            };
+            for waker in wakers.into_values() {
+                waker.wake();
+            }
+        }
+
+        /// Returns a future which completes once the instance is unloaded.
+        pub fn unloaded(&self) -> Unloaded {
+            Unloaded { liveness: self.clone(), key: None }
+        }
+    }
+
+    impl Default for Liveness {
+        fn default() -> Self { Self::new() }
+    }
+
+    impl fmt::Debug for Liveness {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_tuple("Liveness").field(&self.is_live()).finish()
+        }
+    }
+
+    /// The future returned by [`Liveness::unloaded`].
+    #[derive(Debug)]
+    pub struct Unloaded {
+        liveness: Liveness,
+
+        /// The key under which this future's waker is stored, once it has
+        /// been polled.
+        key: Option<usize>,
+    }
+
+    impl Future for Unloaded {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            let this = &mut *self;
+            // The lock is held while checking, so that the instance cannot be
+            // invalidated between the check and the waker being stored:
+            let mut waiting = this.liveness.0.waiting.lock().unwrap();
+            if !this.liveness.is_live() { return Poll::Ready(()); }
+            let key = *this.key.get_or_insert_with(|| {
+                waiting.next_key += 1;
+                waiting.next_key
+            });
+            waiting.wakers.insert(key, cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    impl Drop for Unloaded {
+        fn drop(&mut self) {
+            if let Some(key) = self.key {
+                self.liveness.0.waiting.lock().unwrap().wakers.remove(&key);
+            }
+        }
+    }
+
+    /// An imported interface `M` of a loaded instance of a leaf module, which
+    /// becomes invalid when that instance is unloaded.
+    ///
+    /// An unloaded instance is never freed, so any reference previously
+    /// obtained through a handle remains safe to use, but it should not be
+    /// used after the handle becomes invalid, as the instance may no longer
+    /// be in a consistent state. Instead, the importer is itself unloaded and
+    /// reloaded by the root, so that it imports the new instance.
+    pub struct Handle<M: ?Sized + 'static> {
+        module: &'static M,
+        liveness: Liveness,
+    }
+
+    impl<M: ?Sized + 'static> Handle<M> {
+        pub fn new(module: &'static M, liveness: Liveness) -> Self {
+            Self { module, liveness }
+        }
+
+        /// Returns the imported module, or [`nxs::Error::Unloaded`] if it has
+        /// been unloaded.
+        pub fn get(&self) -> nxs::Result<&'static M> {
+            if self.liveness.is_live() { return Ok(self.module); }
+            Err(nxs::Error::Unloaded { interface: TypeInfo::of::<M>() })
+        }
+
+        pub fn is_live(&self) -> bool {
+            self.liveness.is_live()
+        }
+
+        pub fn liveness(&self) -> &Liveness {
+            &self.liveness
+        }
+    }
+
+    impl<M: ?Sized + 'static> Clone for Handle<M> {
+        fn clone(&self) -> Self {
+            Self { module: self.module, liveness: self.liveness.clone() }
+        }
+    }
+
+    impl<M: ?Sized + 'static> fmt::Debug for Handle<M> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.debug_struct("Handle")
+                .field("interface", &TypeInfo::of::<M>().name)
+                .field("live", &self.is_live())
+                .finish()
+        }
+    }
+
+    /// The result of [`RootModule::dyn_import`]: a [`DynCastRef`] to the
+    /// imported module, which yields the requested interface, together with
+    /// the liveness of the module.
+    pub struct DynHandle {
+        pub module: DynCastRef<'static>,
+        pub liveness: Liveness,
+    }
+
+    impl DynHandle {
+        /// Casts the module to `M`, if this is the interface it yields.
+        pub fn cast<M: Any + ?Sized>(self) -> Option<Handle<M>> {
+            let module = self.module.cast::<M>()?;
+            Some(Handle::new(module, self.liveness))
+        }
     }
 }
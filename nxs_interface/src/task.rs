@@ -0,0 +1,141 @@
+//! The interface through which leaf modules run background tasks.
+//!
+//! Each leaf module is given a [`Spawner`] by its root, through
+//! [`RootModule::spawner`](crate::root::RootModule::spawner), which runs
+//! tasks on behalf of that module until it is unloaded, for example:
+//! ```
+//! # use nxs_interface::{self as nxs, root::RootModule};
+//! #
+//! # struct Settings { server: String }
+//! # struct Connection;
+//! #
+//! # impl Connection {
+//! #     async fn open(_server: &str) -> nxs::Result<Self> { Ok(Connection) }
+//! #     async fn next_line(&self) -> Option<String> { None }
+//! # }
+//! #
+//! # async fn example(root: &'static dyn RootModule, settings: Settings)
+//! # -> nxs::Result<()> {
+//! let connection = Connection::open(&settings.server).await?;
+//! root.spawner().spawn("reader", async move {
+//!     while let Some(line) = connection.next_line().await {
+//!         /* ... */
+//!     }
+//! })?;
+//! # Ok(())
+//! # }
+//! ```
+//! How tasks are run is up to the root, so the same module may be used with
+//! any executor.
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+
+use futures::{
+    channel::oneshot,
+    future::{self, AbortHandle, Aborted, BoxFuture, FutureExt, Shared},
+};
+
+use crate as nxs;
+
+/// A runner of the background tasks of one module.
+///
+/// Each task is cancelled when the module that spawned it is unloaded, or
+/// shut down. If it panics, the panic is reported by the root against that
+/// module.
+pub trait Spawner: Send + Sync {
+    /// Starts running `task`, which is called `name` when it is reported.
+    ///
+    /// Fails if the module has already been unloaded, or if the root does
+    /// not support tasks.
+    fn dyn_spawn(&self, name: &str, task: BoxFuture<'static, ()>)
+    -> nxs::Result<TaskHandle>;
+}
+
+impl dyn Spawner + '_ {
+    /// Starts running `task`, as by [`dyn_spawn`](Spawner::dyn_spawn).
+    pub fn spawn(
+        &self, name: &str, task: impl Future<Output = ()> + Send + 'static,
+    ) -> nxs::Result<TaskHandle> {
+        self.dyn_spawn(name, task.boxed())
+    }
+}
+
+/// A [`Spawner`] which does not support tasks.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NullSpawner;
+
+impl Spawner for NullSpawner {
+    fn dyn_spawn(&self, _name: &str, _task: BoxFuture<'static, ()>)
+    -> nxs::Result<TaskHandle> {
+        Err(nxs::Error::Unsupported { operation: "spawning tasks" })
+    }
+}
+
+/// A handle to a spawned task, through which it may be cancelled, or its
+/// outcome awaited.
+///
+/// Dropping the handle does not cancel the task, which continues to run until
+/// it finishes or its module is unloaded.
+#[derive(Clone, Debug)]
+pub struct TaskHandle {
+    abort: AbortHandle,
+    finished: Arc<AtomicBool>,
+    outcome: Shared<oneshot::Receiver<nxs::Result<()>>>,
+}
+
+impl TaskHandle {
+    /// Wraps `task` so that it may be cancelled through the returned handle,
+    /// and so that any panic in it is caught.
+    ///
+    /// This is for use by roots. The returned future should be run to
+    /// completion, or dropped to cancel the task. Once the task ends, its
+    /// outcome is passed to `on_finish`, and then delivered to the handle:
+    /// `Ok` if it finished, [`nxs::Error::Panicked`] if it panicked, or
+    /// [`nxs::Error::Cancelled`] if it was cancelled.
+    pub fn new(
+        task: BoxFuture<'static, ()>,
+        on_finish: impl FnOnce(&nxs::Result<()>) + Send + 'static,
+    ) -> (BoxFuture<'static, ()>, Self) {
+        let (task, abort) = future::abortable(
+            AssertUnwindSafe(task).catch_unwind(),
+        );
+        let (sender, receiver) = oneshot::channel();
+        let finished = Arc::new(AtomicBool::new(false));
+        let handle = Self {
+            abort, finished: Arc::clone(&finished), outcome: receiver.shared(),
+        };
+        let run = async move {
+            let outcome = match task.await {
+                Ok(Ok(())) => Ok(()),
+                Ok(Err(panic)) => Err(nxs::Error::panicked(panic)),
+                Err(Aborted) => Err(nxs::Error::Cancelled),
+            };
+            on_finish(&outcome);
+            finished.store(true, Ordering::Release);
+            let _ = sender.send(outcome);
+        }.boxed();
+        (run, handle)
+    }
+
+    /// Cancels the task, so that it is dropped the next time it would be
+    /// polled, if it has not already finished.
+    pub fn cancel(&self) {
+        self.abort.abort()
+    }
+
+    /// Tells whether the task has finished, panicked or been cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Acquire)
+    }
+
+    /// Waits for the task to finish, panic or be cancelled, and returns its
+    /// outcome, as passed to `on_finish` by [`new`](Self::new).
+    pub fn join(&self) -> impl Future<Output = nxs::Result<()>> + 'static {
+        self.outcome.clone().map(|outcome| {
+            // If the sender was dropped, so was the task:
+            outcome.unwrap_or(Err(nxs::Error::Cancelled))
+        })
+    }
+}
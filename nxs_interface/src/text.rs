@@ -1,4 +1,10 @@
-use crate::root::LeafModule;
+use crate::{self as nxs, root::{Interface, LeafModule}};
 
 pub trait TextManager: LeafModule {
+    /// Sends the message `text` to `target`, such as a channel or a user.
+    fn send(&self, target: &str, text: &str) -> nxs::Result<()>;
+}
+
+impl Interface for dyn TextManager {
+    const VERSION: &'static str = "0.2.0";
 }
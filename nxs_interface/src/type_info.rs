@@ -0,0 +1,59 @@
+//! The [`TypeInfo`] type.
+
+use std::any::{TypeId, type_name};
+use std::hash::{Hash, Hasher};
+use std::fmt;
+
+/// The [`TypeId`] of a type together with its human-readable name.
+///
+/// Two `TypeInfo`s are equal if and only if their `TypeId`s are equal. The
+/// name is intended only for diagnostics, as given by [`type_name`], and
+/// carries the same caveats: it is not guaranteed to be unique or stable.
+#[derive(Clone, Copy, Debug)]
+pub struct TypeInfo {
+    pub id: TypeId,
+    pub name: &'static str,
+}
+
+impl TypeInfo {
+    /// Returns the `TypeInfo` of the type `T`.
+    pub fn of<T: ?Sized + 'static>() -> Self {
+        Self { id: TypeId::of::<T>(), name: type_name::<T>() }
+    }
+
+    /// Returns the name of the type with all module paths removed, for example
+    /// `dyn TextManager + Send` for
+    /// `dyn nxs_interface::text::TextManager + core::marker::Send`.
+    pub fn short_name(&self) -> String {
+        let mut short = String::with_capacity(self.name.len());
+        let mut rest = self.name;
+        while let Some(start) = rest.find(is_path_char) {
+            short.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c| !is_path_char(c)).unwrap_or(rest.len());
+            short.push_str(rest[..end].rsplit("::").next().unwrap());
+            rest = &rest[end..];
+        }
+        short.push_str(rest);
+        short
+    }
+}
+
+fn is_path_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == ':'
+}
+
+impl PartialEq for TypeInfo {
+    fn eq(&self, other: &Self) -> bool { self.id == other.id }
+}
+impl Eq for TypeInfo {}
+
+impl Hash for TypeInfo {
+    fn hash<H: Hasher>(&self, state: &mut H) { self.id.hash(state) }
+}
+
+impl fmt::Display for TypeInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name)
+    }
+}
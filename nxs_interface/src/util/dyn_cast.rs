@@ -4,6 +4,8 @@ use std::any::{Any, TypeId};
 use std::{rc::Rc, sync::Arc};
 use std::marker::{Sync, Send};
 
+use crate::TypeInfo;
+
 mod tests;
 
 /// Trait providing a generalised form of dynamic typing.
@@ -85,6 +87,14 @@ pub trait DynCast: Any {
     /// resolving to the same base trait, but this is expected to be rare.
     fn castable_types(&self) -> Vec<TypeId>;
 
+    /// Returns a vector of the names of the types to which casting is possible.
+    ///
+    /// The names are given by [`std::any::type_name`], and are listed in the
+    /// same order as the corresponding [`TypeId`]s in the result of
+    /// [`castable_types`](Self::castable_types). They are intended only for
+    /// diagnostic purposes.
+    fn castable_type_names(&self) -> Vec<&'static str>;
+
     /// Attempts to cast a shared reference to a given [`TypeId`].
     ///
     /// If `*self` can be cast to the type `T` for which
@@ -241,6 +251,15 @@ pub trait DynCastExt: DynCast {
         self.dyn_can_cast(TypeId::of::<T>())
     }
 
+    /// Returns a vector describing each type to which casting is possible.
+    ///
+    /// Combines the results of [`castable_types`](DynCast::castable_types) and
+    /// [`castable_type_names`](DynCast::castable_type_names).
+    fn castable_type_infos(&self) -> Vec<TypeInfo> {
+        self.castable_types().into_iter().zip(self.castable_type_names())
+            .map(|(id, name)| TypeInfo { id, name }).collect()
+    }
+
     /// Attempts to cast a shared reference to a given type.
     /// 
     /// If `*self` can be cast to type `T`, returns `Some` with the given shared
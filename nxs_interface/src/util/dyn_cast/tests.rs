@@ -3,11 +3,11 @@
 
 use std::collections::HashSet;
 use std::iter::FromIterator;
-use std::any::{Any, TypeId};
+use std::any::{Any, TypeId, type_name};
 use std::rc::Rc;
 use std::sync::Arc;
 
-use crate::util::dyn_cast::{DynCast, DynCastExt};
+use crate::{TypeInfo, util::dyn_cast::{DynCast, DynCastExt}};
 
 macro_rules! test_castable_types {
     ($value:ident, types($($type:ty,)*)) => {
@@ -15,6 +15,15 @@ macro_rules! test_castable_types {
             HashSet::<TypeId>::from_iter($value.castable_types()),
             HashSet::<TypeId>::from_iter([$(TypeId::of::<$type>()),*]),
         );
+        assert_eq!(
+            HashSet::<TypeInfo>::from_iter($value.castable_type_infos()),
+            HashSet::<TypeInfo>::from_iter([$(TypeInfo::of::<$type>()),*]),
+        );
+        for info in $value.castable_type_infos() {
+            assert_eq!(Some(info.name), [$(
+                (TypeId::of::<$type>(), type_name::<$type>())
+            ),*].iter().find(|(id, _)| *id == info.id).map(|(_, n)| *n));
+        }
     }
 }
 
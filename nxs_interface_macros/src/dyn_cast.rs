@@ -62,6 +62,7 @@ pub fn derive(input: TokenStream) -> syn::Result<TokenStream> {
     let Sync: Path     = pq!(::std::marker::Sync);
     let Any: Path      = pq!(::std::any::Any);
     let TypeId: Type   = pq!(::std::any::TypeId);
+    let type_name: Path = pq!(::std::any::type_name);
     let Option: Type   = pq!(::std::option::Option);
     let Vec: Type      = pq!(::std::vec::Vec);
     let Box: Type      = pq!(::std::boxed::Box);
@@ -185,6 +186,9 @@ pub fn derive(input: TokenStream) -> syn::Result<TokenStream> {
             fn castable_types(&self) -> #Vec<#TypeId> {
                 vec![#(#TypeId::of::<#castable>()),*]
             }
+            fn castable_type_names(&self) -> #Vec<&'static str> {
+                vec![#(#type_name::<#castable>()),*]
+            }
             #(#impl_dyn_cast_methods)*
             #impl_dyn_cast_arc
         }
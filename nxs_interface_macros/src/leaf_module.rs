@@ -1,12 +1,27 @@
+//! Automatic derivation of the `LeafModule` trait.
+
 use proc_macro2::TokenStream;
 use syn::{
-    Error, DeriveInput, Path, Type, Meta, NestedMeta, Ident,
-    parse2 as parse, parse_quote as pq
+    Error, DeriveInput, Path, Type, Meta, NestedMeta, Ident, Lit, LitStr,
+    MetaList, Attribute, parse2 as parse, parse_quote as pq
 };
 use quote::quote as q;
 
 use crate::util::static_impl_generics;
 
+/// The options given in `leaf_module` helper attributes.
+#[derive(Default)]
+struct Options {
+    crate_path: Option<Path>,
+    name: Option<LitStr>,
+    version: Option<LitStr>,
+    provides: Vec<Path>,
+    requires: Vec<Path>,
+    optional: Vec<Path>,
+    scope: Option<LitStr>,
+    shutdown: bool,
+}
+
 pub fn derive(input: TokenStream) -> syn::Result<TokenStream> {
     #![allow(non_snake_case)]
 
@@ -16,55 +31,168 @@ pub fn derive(input: TokenStream) -> syn::Result<TokenStream> {
         = static_impl_generics(generics.split_for_impl());
 
     // Extract options from helper attributes:
-    let mut crate_path: Option<Path> = None;
-    const ATTR_ERR: &str = "Invalid argument(s) to `leaf_module` attribute.";
-    const PATH_ERR: &str = "`path` may not be specified more than once.";
-    for attr in attrs {
-        if !attr.path.is_ident("leaf_module") { continue; }
-        let list = if let Meta::List(ls) = attr.parse_meta()? { Ok(ls) }
-                   else { Err(Error::new_spanned(attr, ATTR_ERR)) }?;
-        for item in list.nested {
-            let meta = if let NestedMeta::Meta(m) = item { Ok(m) }
-                       else { Err(Error::new_spanned(item, ATTR_ERR)) }?;
-            let name = meta.path().get_ident().map(Ident::to_string);
-            match (name.as_deref(), meta) {
-                (Some("crate"), Meta::List(list)) if list.nested.len() == 1 => {
-                    match (&crate_path, list.nested.into_iter().next()) {
-                        (None, Some(NestedMeta::Meta(Meta::Path(path)))) => {
-                            crate_path = Some(path); Ok(())
-                        }
-                        (None, nm) => Err(Error::new_spanned(nm, ATTR_ERR)),
-                        (_,    nm) => Err(Error::new_spanned(nm, PATH_ERR)),
-                    }
-                }
-                (_, mt) => Err(Error::new_spanned(mt, ATTR_ERR)),
-            }?
-        }
-    }
-    let crate_path = crate_path.unwrap_or_else(|| pq!(::nxs_interface));
+    let mut options = Options::default();
+    for attr in attrs { read_attr(attr, &mut options)?; }
+    let crate_path = options.crate_path.unwrap_or_else(|| pq!(::nxs_interface));
+    let module_name = options.name.map_or_else(|| ident.to_string(), |n| n.value());
+    let version = match options.version {
+        Some(version) => q!(#version),
+        None          => q!(::std::env!("CARGO_PKG_VERSION")),
+    };
+    let provides = options.provides;
+    let requires = options.requires;
+    let optional = options.optional;
+    let scope = match options.scope.as_ref().map(LitStr::value).as_deref() {
+        None | Some("singleton") => q!(Singleton),
+        Some("per_importer")     => q!(PerImporter),
+        Some(_)                  => q!(Transient),
+    };
 
     // Define paths and types for quote interpolation:
-    let LeafModule: Path = pq!(#crate_path::root::LeafModule);
-    let RootModule: Path = pq!(#crate_path::root::RootModule);
-    let Pin: Type        = pq!(::std::pin::Pin);
-    let Box: Type        = pq!(::std::boxed::Box);
-    let Future: Path     = pq!(::std::future::Future);
-    let Send: Path       = pq!(::std::marker::Send);
+    let LeafModule: Path    = pq!(#crate_path::root::LeafModule);
+    let RootModule: Path    = pq!(#crate_path::root::RootModule);
+    let Manifest: Path      = pq!(#crate_path::root::Manifest);
+    let Version: Path       = pq!(#crate_path::root::Version);
+    let InterfaceInfo: Path = pq!(#crate_path::root::InterfaceInfo);
+    let Scope: Path         = pq!(#crate_path::root::Scope);
+    let Pin: Type           = pq!(::std::pin::Pin);
+    let Box: Type           = pq!(::std::boxed::Box);
+    let Future: Path        = pq!(::std::future::Future);
+    let Send: Path          = pq!(::std::marker::Send);
 
     let impl_type = q!(#ident#type_gen);
     let BoxFuture = |a, T| q!(#Pin<#Box<dyn #Future<Output = #T> + #Send + #a>>);
     let result = q!(#crate_path::Result<#Box<dyn #LeafModule + 'static>>);
     let result = BoxFuture(q!('static), result);
+    let unit_result = BoxFuture(q!('static), q!(#crate_path::Result<()>));
+
+    // Delegate to an inherent `shutdown` method only if one is declared:
+    let shutdown = if options.shutdown { q!{
+        fn shutdown(&'static self) -> #unit_result {
+            #Box::pin(async move {
+                <#impl_type>::shutdown(self).await.map_err(#crate_path::Error::from)
+            })
+        }
+    }} else { q!() };
 
     Ok(q!{
         impl#impl_gen #LeafModule for #impl_type #where_clause {
             fn dyn_load(root: &'static (dyn #RootModule + 'static))
             -> #result where Self: Sized {
-                #Box::pin(async move {Ok(
-                    #Box::new(<#impl_type>::load(root).await?)
-                    as #Box<dyn #LeafModule>
-                )})
+                #Box::pin(async move {
+                    match <#impl_type>::load(root).await {
+                        Ok(module) => Ok(#Box::new(module) as #Box<dyn #LeafModule>),
+                        Err(error) => Err(#crate_path::Error::from(error)
+                                          .load_failed(#module_name)),
+                    }
+                })
             }
+            fn manifest() -> #Manifest where Self: Sized {
+                #Manifest {
+                    name: #module_name,
+                    version: #Version::parse(#version).expect(
+                        "The version of a `LeafModule` must be valid semver."
+                    ),
+                    provides: vec![#(#InterfaceInfo::of::<dyn #provides>()),*],
+                    requires: vec![#(#InterfaceInfo::of::<dyn #requires>()),*],
+                    optional: vec![#(#InterfaceInfo::of::<dyn #optional>()),*],
+                    scope: #Scope::#scope,
+                }
+            }
+            fn dyn_manifest(&self) -> #Manifest {
+                <Self as #LeafModule>::manifest()
+            }
+            #shutdown
         }
     })
 }
+
+const ATTR_ERR: &str = "Invalid argument(s) to `leaf_module` attribute.";
+
+fn read_attr(attr: Attribute, options: &mut Options) -> syn::Result<()> {
+    const PATH_ERR: &str = "`crate` may not be specified more than once.";
+    const NAME_ERR: &str = "`name` may not be specified more than once.";
+    const VERS_ERR: &str = "`version` may not be specified more than once.";
+    const SEMVER_ERR: &str = "`version` must be a valid semantic version.";
+    const SCOPE_ERR: &str = "`scope` may not be specified more than once.";
+    const SCOPES_ERR: &str
+        = "`scope` must be \"singleton\", \"per_importer\" or \"transient\".";
+
+    if !attr.path.is_ident("leaf_module") { return Ok(()); }
+    let list = if let Meta::List(ls) = attr.parse_meta()? { Ok(ls) }
+               else { Err(Error::new_spanned(attr, ATTR_ERR)) }?;
+    for item in list.nested {
+        let meta = if let NestedMeta::Meta(m) = item { Ok(m) }
+                   else { Err(Error::new_spanned(item, ATTR_ERR)) }?;
+        let name = meta.path().get_ident().map(Ident::to_string);
+        match (name.as_deref(), meta) {
+            (Some("crate"), Meta::List(list)) if list.nested.len() == 1 => {
+                match (&options.crate_path, list.nested.into_iter().next()) {
+                    (None, Some(NestedMeta::Meta(Meta::Path(path)))) => {
+                        options.crate_path = Some(path); Ok(())
+                    }
+                    (None, nm) => Err(Error::new_spanned(nm, ATTR_ERR)),
+                    (_,    nm) => Err(Error::new_spanned(nm, PATH_ERR)),
+                }
+            }
+            (Some("name"), Meta::NameValue(nv)) => {
+                match (&options.name, nv.lit) {
+                    (None, Lit::Str(name)) => {
+                        options.name = Some(name); Ok(())
+                    }
+                    (None, lit) => Err(Error::new_spanned(lit, ATTR_ERR)),
+                    (_,    lit) => Err(Error::new_spanned(lit, NAME_ERR)),
+                }
+            }
+            (Some("version"), Meta::NameValue(nv)) => {
+                match (&options.version, nv.lit) {
+                    (None, Lit::Str(version)) => {
+                        if semver::Version::parse(&version.value()).is_err() {
+                            return Err(Error::new_spanned(version, SEMVER_ERR));
+                        }
+                        options.version = Some(version); Ok(())
+                    }
+                    (None, lit) => Err(Error::new_spanned(lit, ATTR_ERR)),
+                    (_,    lit) => Err(Error::new_spanned(lit, VERS_ERR)),
+                }
+            }
+            (Some("scope"), Meta::NameValue(nv)) => {
+                match (&options.scope, nv.lit) {
+                    (None, Lit::Str(scope)) => {
+                        let scopes = ["singleton", "per_importer", "transient"];
+                        if !scopes.contains(&scope.value().as_str()) {
+                            return Err(Error::new_spanned(scope, SCOPES_ERR));
+                        }
+                        options.scope = Some(scope); Ok(())
+                    }
+                    (None, lit) => Err(Error::new_spanned(lit, ATTR_ERR)),
+                    (_,    lit) => Err(Error::new_spanned(lit, SCOPE_ERR)),
+                }
+            }
+            (Some("shutdown"), Meta::Path(_)) if !options.shutdown => {
+                options.shutdown = true; Ok(())
+            }
+            (Some("provides"), Meta::List(list)) => {
+                read_traits(list, &mut options.provides)
+            }
+            (Some("requires"), Meta::List(list)) => {
+                read_traits(list, &mut options.requires)
+            }
+            (Some("optional"), Meta::List(list)) => {
+                read_traits(list, &mut options.optional)
+            }
+            (_, mt) => Err(Error::new_spanned(mt, ATTR_ERR)),
+        }?
+    }
+    Ok(())
+}
+
+fn read_traits(list: MetaList, traits: &mut Vec<Path>) -> syn::Result<()> {
+    for item in list.nested {
+        match item {
+            NestedMeta::Meta(Meta::Path(path)) => traits.push(path),
+            _ => return Err(Error::new_spanned(item, ATTR_ERR)),
+        }
+    }
+    Ok(())
+}
@@ -10,16 +10,16 @@ mod dyn_cast;
 #[proc_macro_derive(DynCast, attributes(dyn_cast))]
 pub fn derive_dyn_cast(input: TokenStream) -> TokenStream {
     dyn_cast::derive(input.into()).unwrap_or_else(
-        |e| e.into_compile_error().into()
+        |e| e.into_compile_error()
     ).into()
 }
 
 
 mod leaf_module;
 
-#[proc_macro_derive(LeafModule)]
+#[proc_macro_derive(LeafModule, attributes(leaf_module))]
 pub fn derive_leaf_module(input: TokenStream) -> TokenStream {
     leaf_module::derive(input.into()).unwrap_or_else(
-        |e| e.into_compile_error().into()
+        |e| e.into_compile_error()
     ).into()
 }
@@ -32,10 +32,7 @@ pub fn static_impl_generics<'a>(
     let mut impl_gen: AngleBracketList<GenericParam>
         = parse(impl_gen.to_token_stream()).expect(PARSE_ERR);
     impl_gen.items = take(&mut impl_gen.items).into_pairs().filter(|pair| {
-        match pair.value() {
-            GenericParam::Lifetime(_) => false,
-            _                         => true,
-        }
+        !matches!(pair.value(), GenericParam::Lifetime(_))
     }).collect();
 
     // Replace all lifetimes with 'static in the list of generic arguments:
@@ -50,13 +47,10 @@ pub fn static_impl_generics<'a>(
 
     // Filter out all type parameters from the `where` clause (if there is one):
     let mut where_clause: Option<WhereClause>
-        = where_clause.map(WhereClause::clone);
+        = where_clause.cloned();
     if let Some(WhereClause { ref mut predicates, .. }) = &mut where_clause {
         *predicates = take(predicates).into_pairs().filter(|pair| {
-            match pair.value() {
-                WherePredicate::Lifetime(_) => false,
-                _                           => true,
-            }
+            !matches!(pair.value(), WherePredicate::Lifetime(_))
         }).collect();
     }
 
@@ -1,14 +1,34 @@
 use nxs_interface::{
     self as nxs,
     util::dyn_cast::DynCast,
-    root::{LeafModule, RootModule},
+    root::{LeafModule, RootModule, Handle},
     text::TextManager,
+    config::ConfigManager,
 };
+use serde::Deserialize;
 
 #[derive(DynCast, LeafModule)]
-struct Commands {
+#[dyn_cast(base_traits(LeafModule))]
+#[leaf_module(requires(TextManager, ConfigManager))]
+pub struct Commands {
     root: &'static dyn RootModule,
-    text: &'static dyn TextManager,
+    text: Handle<dyn TextManager>,
+    settings: Settings,
+}
+
+/// The settings of [`Commands`], read from its section of the configuration,
+/// or from the section of its instance if it is a named instance.
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Settings {
+    /// The prefix distinguishing commands from other messages.
+    prefix: String,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { prefix: "!".to_string() }
+    }
 }
 
 impl Commands {
@@ -16,6 +36,24 @@ impl Commands {
         Ok(Commands {
             root,
             text: root.import::<dyn TextManager>().await?,
+            settings: root.import::<dyn ConfigManager>().await?.get()?
+                .section()?,
         })
     }
+
+    /// Returns the command in `message` without its prefix, or `None` if
+    /// `message` is not a command.
+    pub fn command<'a>(&self, message: &'a str) -> Option<&'a str> {
+        message.strip_prefix(&self.settings.prefix)
+    }
+
+    /// Replies to a command from `target` with `text`, through the
+    /// [`TextManager`].
+    pub fn reply(&self, target: &str, text: &str) -> nxs::Result<()> {
+        nxs::nxs_debug!(self.root.logger(), { target: target }, "replying");
+        self.text.get()?.send(target, text)
+    }
 }
+
+#[cfg(feature = "plugin")]
+nxs::export_plugin!(Commands);
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use futures::{channel::mpsc, stream::{BoxStream, StreamExt}};
+use nxs_interface::{
+    self as nxs,
+    util::dyn_cast::DynCast,
+    root::{LeafModule, RootModule, Liveness},
+    events::{EventBus, DynEvent, Subscription},
+};
+
+/// An [`EventBus`] which queues each event for its subscribers as soon as it
+/// is published.
+///
+/// Subscriptions are keyed by the [`TypeId`](std::any::TypeId) of the type to
+/// which they subscribe. The stream of a subscription ends as soon as its
+/// module is unloaded. Those of an unloaded module, and those whose streams
+/// have been dropped, are removed the next time an event is published to them
+/// or a new subscription is made.
+#[derive(DynCast, LeafModule)]
+#[dyn_cast(base_traits(LeafModule, EventBus))]
+#[leaf_module(provides(EventBus))]
+pub struct StdEventBus {
+    subscribers: Mutex<HashMap<Subscription, Vec<Subscriber>>>,
+}
+
+/// A subscription to a [`StdEventBus`].
+struct Subscriber {
+    /// The liveness of the subscribing module, or `None` if the root itself
+    /// subscribed.
+    liveness: Option<Liveness>,
+    sender: mpsc::UnboundedSender<DynEvent>,
+}
+
+impl Subscriber {
+    /// Tells whether the subscribing module is loaded and is still receiving
+    /// events.
+    fn is_live(&self) -> bool {
+        self.liveness.as_ref().is_none_or(Liveness::is_live)
+            && !self.sender.is_closed()
+    }
+}
+
+impl StdEventBus {
+    async fn load(_root: &'static dyn RootModule)
+    -> nxs::Result<StdEventBus> {
+        Ok(StdEventBus {
+            subscribers: Mutex::default(),
+        })
+    }
+}
+
+impl EventBus for StdEventBus {
+    fn dyn_publish(&self, event: DynEvent) {
+        let mut keys = vec![Subscription::Exact(event.type_id())];
+        for id in event.castable_types() {
+            let key = Subscription::Castable(id);
+            if !keys.contains(&key) { keys.push(key); }
+        }
+        let mut subscribers = self.subscribers.lock().unwrap();
+        for key in keys {
+            let list = match subscribers.get_mut(&key) {
+                Some(list) => list,
+                None => continue,
+            };
+            list.retain(|subscriber| {
+                subscriber.is_live()
+                    && subscriber.sender.unbounded_send(event.clone()).is_ok()
+            });
+            if list.is_empty() { subscribers.remove(&key); }
+        }
+    }
+
+    fn dyn_subscribe(&self, root: &dyn RootModule, to: Subscription)
+    -> BoxStream<'static, DynEvent> {
+        let (sender, receiver) = mpsc::unbounded();
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers.retain(|_, list| {
+            list.retain(Subscriber::is_live);
+            !list.is_empty()
+        });
+        let liveness = root.liveness();
+        let unloaded = liveness.as_ref().map(Liveness::unloaded);
+        subscribers.entry(to).or_default().push(Subscriber { liveness, sender });
+        match unloaded {
+            Some(unloaded) => receiver.take_until(unloaded).boxed(),
+            None => receiver.boxed(),
+        }
+    }
+}
+
+#[cfg(feature = "plugin")]
+nxs::export_plugin!(StdEventBus);
@@ -0,0 +1,501 @@
+//! The configuration file of the standard root, and the [`StdConfig`] module
+//! through which leaf modules read their sections of it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, Once, RwLock};
+use std::time::{Duration, SystemTime};
+
+use nxs_interface::{
+    self as nxs,
+    util::dyn_cast::{DynCast, DynCastExt},
+    root::{LeafModule, RootModule},
+    config::{ConfigManager, Table},
+    logging::Level,
+};
+use futures::{channel::mpsc, stream::{BoxStream, StreamExt}};
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{
+    LoadConfig, LogConfig, LogSink, MetricsConfig, Registry, ShutdownConfig,
+    StdRootModule,
+    importer::Importer,
+};
+
+/// The greatest number of seconds that may be configured for a timeout or an
+/// interval.
+const MAX_SECONDS: f64 = Duration::MAX.as_secs_f64();
+
+/// The interval at which the configuration file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The contents of a configuration file, in TOML.
+///
+/// Each top-level table other than `[root]` is the section for the module or
+/// named instance of the same name, which it reads through [`StdConfig`]. The
+/// `[root]` table configures the root itself, for example:
+/// ```toml
+/// [root]
+/// modules = ["nxs_std_text", "nxs_std_cmds"]
+/// plugins = "plugins"
+/// load_timeout = 30
+/// shutdown_timeout = 10
+/// log_level = "info"
+///
+/// [root.module_load_timeouts]
+/// StdTextManager = 60
+///
+/// [root.module_shutdown_timeouts]
+/// Commands = 2.5
+///
+/// [root.providers]
+/// TextManager = "StdTextManager"
+///
+/// [root.priorities]
+/// StdTextManager = 10
+///
+/// [root.instances]
+/// libera = "StdTextManager"
+///
+/// [root.import_allow_lists]
+/// Commands = ["Storage"]
+///
+/// [root.module_log_levels]
+/// Commands = "debug"
+///
+/// [[root.log_sinks]]
+/// kind = "stderr"
+///
+/// [[root.log_sinks]]
+/// kind = "file"
+/// path = "nexus.log"
+/// format = "json"
+///
+/// [root.metrics]
+/// address = "127.0.0.1:9184"
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// The path from which the configuration was read.
+    pub path: PathBuf,
+
+    pub root: RootConfig,
+
+    /// Every top-level table other than `[root]`.
+    pub sections: toml::Table,
+}
+
+/// The `[root]` table of a [`Config`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RootConfig {
+    /// The names of the sets of leaf modules to enable, each of which is
+    /// either built in or the name of a plugin in `plugins`.
+    pub modules: Vec<String>,
+
+    /// The directory containing plugins, relative to the configuration file.
+    pub plugins: Option<PathBuf>,
+
+    /// The number of seconds allowed for each module to load.
+    pub load_timeout: Option<f64>,
+
+    /// The number of seconds allowed for specific modules, by name, to load.
+    pub module_load_timeouts: HashMap<String, f64>,
+
+    /// The number of seconds allowed for each module to shut down.
+    pub shutdown_timeout: Option<f64>,
+
+    /// The number of seconds allowed for specific modules, by name, to shut
+    /// down.
+    pub module_shutdown_timeouts: HashMap<String, f64>,
+
+    /// The name of the module selected to provide each interface, by the
+    /// interface's name.
+    pub providers: HashMap<String, String>,
+
+    /// The priorities of specific modules, by name, among the providers of
+    /// the same interfaces.
+    pub priorities: HashMap<String, i32>,
+
+    /// The name of the module of which to register each named instance, by
+    /// the instance's name.
+    pub instances: HashMap<String, String>,
+
+    /// Whether to restrict every module to importing only the interfaces
+    /// declared in its manifest, and those in `import_allow_lists`.
+    pub restrict_imports: bool,
+
+    /// The names of interfaces that specific modules, by name, may import in
+    /// addition to those declared in their manifests. Each module listed is
+    /// restricted to these, even if `restrict_imports` is false.
+    pub import_allow_lists: HashMap<String, Vec<String>>,
+
+    /// The least severe level of log message written for each module.
+    #[serde(deserialize_with = "parse_some")]
+    pub log_level: Option<Level>,
+
+    /// The least severe level of log message written for specific modules, by
+    /// name, or for targets of the `log` and `tracing` facades, by prefix.
+    #[serde(deserialize_with = "parse_values")]
+    pub module_log_levels: HashMap<String, Level>,
+
+    /// The sinks to which log messages are written, with any file paths
+    /// relative to the configuration file. If empty, messages are written to
+    /// standard error.
+    pub log_sinks: Vec<LogSink>,
+
+    /// Where to expose the metrics of the root and its modules.
+    pub metrics: MetricsTable,
+}
+
+/// The `[root.metrics]` table of a [`Config`].
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsTable {
+    /// The address and port on which to serve the metrics over HTTP.
+    pub address: Option<SocketAddr>,
+
+    /// The file to which to write the metrics periodically, relative to the
+    /// configuration file.
+    pub file: Option<PathBuf>,
+
+    /// The number of seconds between writes of `file`.
+    pub interval: Option<f64>,
+}
+
+/// An error in reading or parsing a configuration file.
+#[derive(Debug)]
+pub struct ConfigError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message.trim_end())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl RootConfig {
+    fn validate(&self) -> Result<(), String> {
+        let is_valid = |t: f64| seconds(t).is_some();
+        let timeouts = [
+            ("load_timeout", "module_load_timeouts",
+             self.load_timeout, &self.module_load_timeouts),
+            ("shutdown_timeout", "module_shutdown_timeouts",
+             self.shutdown_timeout, &self.module_shutdown_timeouts),
+        ];
+        for (key, module_key, timeout, module_timeouts) in timeouts {
+            if !timeout.is_none_or(is_valid) {
+                return Err(format!("`{}` must be a number of seconds from 0 \
+                                    to {:e}", key, MAX_SECONDS));
+            }
+            for (name, &timeout) in module_timeouts {
+                if !is_valid(timeout) {
+                    return Err(format!("`{}.{}` must be a number of seconds \
+                                        from 0 to {:e}",
+                                       module_key, name, MAX_SECONDS));
+                }
+            }
+        }
+        let is_positive = |t: f64| seconds(t).is_some_and(|t| !t.is_zero());
+        if !self.metrics.interval.is_none_or(is_positive) {
+            return Err(format!("`metrics.interval` must be a positive number \
+                                of seconds up to {:e}", MAX_SECONDS));
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    /// Reads the configuration file at `path`.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let error = |message: String| {
+            ConfigError { path: path.to_path_buf(), message }
+        };
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| error(e.to_string()))?;
+        Self::parse(path, &text).map_err(error)
+    }
+
+    /// Parses `text` as the contents of a configuration file at `path`.
+    pub fn parse(path: &Path, text: &str) -> Result<Self, String> {
+        let mut sections: toml::Table = text.parse()
+            .map_err(|e: toml::de::Error| e.to_string())?;
+        let root = match sections.remove("root") {
+            Some(root) => RootConfig::deserialize(root)
+                .map_err(|e| format!("in [root]: {}", e))?,
+            None => RootConfig::default(),
+        };
+        root.validate().map_err(|e| format!("in [root]: {}", e))?;
+        if let Some((key, _)) = sections.iter().find(|(_, v)| !v.is_table()) {
+            return Err(format!("`{}` must be a table", key));
+        }
+        Ok(Self { path: path.to_path_buf(), root, sections })
+    }
+
+    /// Returns the plugins directory, if one is configured.
+    pub fn plugins_dir(&self) -> Option<PathBuf> {
+        let dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+        self.root.plugins.as_ref().map(|plugins| dir.join(plugins))
+    }
+
+    /// Registers the configured named instances in `registry`, and applies the
+    /// configured provider selections, priorities and import restrictions to
+    /// it, which must already contain the modules they name.
+    pub fn apply_to(&self, registry: &mut Registry) -> nxs::Result<()> {
+        let mut instances: Vec<_> = self.root.instances.iter().collect();
+        instances.sort();
+        for (name, module) in instances {
+            registry.add_instance(name, module)?;
+        }
+        if self.root.restrict_imports {
+            let names: Vec<_> = registry.manifests().map(|m| m.name).collect();
+            for name in names {
+                registry.restrict(name, &[])?;
+            }
+        }
+        // The allow-list of a module also applies to its named instances, so
+        // those of the instances themselves are applied afterwards:
+        let mut allow_lists: Vec<_> = self.root.import_allow_lists.iter()
+            .collect();
+        allow_lists.sort_by_key(|&(name, _)| {
+            (self.root.instances.contains_key(name), name)
+        });
+        for (name, allowed) in allow_lists {
+            let allowed: Vec<&str> = allowed.iter().map(String::as_str).collect();
+            registry.restrict(name, &allowed)?;
+        }
+        for (name, &priority) in &self.root.priorities {
+            registry.set_priority(name, priority)?;
+        }
+        for (interface, module) in &self.root.providers {
+            registry.select(interface, module)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the configured time allowed for modules to load.
+    ///
+    /// Times out of range, which [`parse`](Self::parse) rejects, are ignored
+    /// here and below, in favour of the defaults.
+    pub fn loading(&self) -> LoadConfig {
+        let mut config = LoadConfig::default();
+        if let Some(timeout) = self.root.load_timeout.and_then(seconds) {
+            config.timeout = timeout;
+        }
+        config.module_timeouts = durations(&self.root.module_load_timeouts);
+        config
+    }
+
+    /// Returns the configured time allowed for modules to shut down.
+    pub fn shutdown(&self) -> ShutdownConfig {
+        let mut config = ShutdownConfig::default();
+        if let Some(timeout) = self.root.shutdown_timeout.and_then(seconds) {
+            config.timeout = timeout;
+        }
+        config.module_timeouts = durations(&self.root.module_shutdown_timeouts);
+        config
+    }
+
+    /// Returns the configured log levels and sinks.
+    pub fn logging(&self) -> LogConfig {
+        let mut config = LogConfig::default();
+        if let Some(level) = self.root.log_level {
+            config.level = level;
+        }
+        config.module_levels = self.root.module_log_levels.clone();
+        if !self.root.log_sinks.is_empty() {
+            let dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+            config.sinks = self.root.log_sinks.iter().cloned().map(|sink| {
+                match sink {
+                    LogSink::File { path, format, max_bytes, keep } => {
+                        let path = dir.join(path);
+                        LogSink::File { path, format, max_bytes, keep }
+                    }
+                    sink => sink,
+                }
+            }).collect();
+        }
+        config
+    }
+
+    /// Returns the configured means of exposing metrics.
+    ///
+    /// An interval out of range, which [`parse`](Self::parse) rejects, is
+    /// ignored in favour of the default.
+    pub fn metrics(&self) -> MetricsConfig {
+        let mut config = MetricsConfig::default();
+        let table = &self.root.metrics;
+        config.address = table.address;
+        let dir = self.path.parent().unwrap_or_else(|| Path::new(""));
+        config.file = table.file.as_ref().map(|file| dir.join(file));
+        if let Some(interval) = table.interval.and_then(seconds) {
+            if !interval.is_zero() { config.interval = interval; }
+        }
+        config
+    }
+}
+
+/// Deserializes a string, and parses it as a `T`.
+fn parse_some<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where D: Deserializer<'de>, T: FromStr, T::Err: fmt::Display {
+    let text = String::deserialize(deserializer)?;
+    text.parse().map(Some).map_err(de::Error::custom)
+}
+
+/// Deserializes a table of strings, and parses each as a `T`.
+fn parse_values<'de, D, T>(deserializer: D)
+-> Result<HashMap<String, T>, D::Error>
+where D: Deserializer<'de>, T: FromStr, T::Err: fmt::Display {
+    let table = HashMap::<String, String>::deserialize(deserializer)?;
+    table.into_iter().map(|(key, text)| {
+        match text.parse() {
+            Ok(value) => Ok((key, value)),
+            Err(e) => Err(de::Error::custom(format!("`{}`: {}", key, e))),
+        }
+    }).collect()
+}
+
+/// Converts a number of seconds to a duration, or `None` if it is negative,
+/// not a number, or too large.
+fn seconds(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+/// Converts numbers of seconds to durations, keeping their keys, and omitting
+/// those out of range.
+fn durations(times: &HashMap<String, f64>) -> HashMap<String, Duration> {
+    times.iter()
+        .filter_map(|(name, &t)| Some((name.clone(), seconds(t)?)))
+        .collect()
+}
+
+/// The current configuration of a [`StdRootModule`], and the senders of the
+/// change notifications requested from it.
+pub(crate) struct ConfigStore {
+    config: RwLock<Config>,
+    watchers: Mutex<HashMap<String, Vec<mpsc::UnboundedSender<()>>>>,
+    polling: Once,
+}
+
+impl ConfigStore {
+    pub fn new(config: Config) -> Self {
+        Self {
+            config: RwLock::new(config), watchers: Mutex::default(),
+            polling: Once::new(),
+        }
+    }
+}
+
+impl StdRootModule {
+    /// Returns the current configuration.
+    pub fn config(&self) -> Config {
+        self.config.config.read().unwrap().clone()
+    }
+
+    /// Reads the configuration file again, notifying each module watching its
+    /// section if that section has changed.
+    ///
+    /// If the file cannot be read or is invalid, the current configuration is
+    /// kept, and an error is returned.
+    pub fn reload_config(&self) -> Result<(), ConfigError> {
+        let path = self.config.config.read().unwrap().path.clone();
+        if path.as_os_str().is_empty() { return Ok(()); }
+        let new = Config::load(&path)?;
+        let old = std::mem::replace(&mut *self.config.config.write().unwrap(), new);
+        let new = self.config.config.read().unwrap();
+        let logging = new.logging();
+        self.logs.set_levels(&logging);
+        if old.root.log_sinks != new.root.log_sinks {
+            self.logs.set_sinks(&logging.sinks);
+        }
+        let mut watchers = self.config.watchers.lock().unwrap();
+        for (module, senders) in watchers.iter_mut() {
+            if old.sections.get(module) != new.sections.get(module) {
+                senders.retain(|sender| sender.unbounded_send(()).is_ok());
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts a thread which reloads the configuration whenever the
+    /// modification time of its file changes, unless one is already started.
+    fn poll_config(&'static self) {
+        let path = self.config.config.read().unwrap().path.clone();
+        if path.as_os_str().is_empty() { return; }
+        self.config.polling.call_once(move || {
+            let modified = move || -> Option<SystemTime> {
+                std::fs::metadata(&path).and_then(|m| m.modified()).ok()
+            };
+            std::thread::spawn(move || {
+                let mut last = modified();
+                loop {
+                    std::thread::sleep(POLL_INTERVAL);
+                    let now = modified();
+                    if now != last {
+                        last = now;
+                        // An invalid file is ignored until it is corrected:
+                        let _ = self.reload_config();
+                    }
+                }
+            });
+        });
+    }
+}
+
+/// The standard provider of [`ConfigManager`], which reads the configuration
+/// of the [`StdRootModule`] loading it.
+///
+/// A separate instance is loaded for each importing module, which reads the
+/// section named after that module. If the root itself imports it, the
+/// section is that named `root`, which is always empty, as the `[root]` table
+/// configures the root itself.
+///
+/// The configuration file is checked for changes once each second, after the
+/// first time any module asks to be notified of changes.
+#[derive(DynCast, LeafModule)]
+#[dyn_cast(base_traits(LeafModule, ConfigManager))]
+#[leaf_module(provides(ConfigManager), scope = "per_importer")]
+pub struct StdConfig {
+    root: &'static StdRootModule,
+
+    /// The name of the importing module, or `root` if it is the root.
+    module: &'static str,
+}
+
+impl StdConfig {
+    async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+        let importer = root.cast_ref::<Importer>().ok_or_else(|| {
+            nxs::Error::module("StdConfig", "must be loaded by a `StdRootModule`")
+        })?;
+        let module = root.importer().unwrap_or("root");
+        Ok(StdConfig { root: importer.root(), module })
+    }
+}
+
+impl ConfigManager for StdConfig {
+    fn module(&self) -> &'static str {
+        self.module
+    }
+
+    fn raw_section(&self) -> Table {
+        let config = self.root.config.config.read().unwrap();
+        config.sections.get(self.module)
+            .and_then(|section| section.as_table())
+            .cloned().unwrap_or_default()
+    }
+
+    fn changes(&self) -> BoxStream<'static, ()> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.root.config.watchers.lock().unwrap()
+            .entry(self.module.to_string()).or_default().push(sender);
+        self.root.poll_config();
+        receiver.boxed()
+    }
+}
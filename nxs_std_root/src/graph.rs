@@ -0,0 +1,273 @@
+//! The graph of the modules of a [`Registry`] or [`StdRootModule`] and the
+//! interfaces they import from each other, for export as Graphviz DOT or JSON.
+
+use std::fmt::Write;
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    root::{InterfaceInfo, Version},
+};
+use serde_json::{Value, json};
+
+use crate::{Registry, StdRootModule};
+
+/// The modules of a [`Registry`] or [`StdRootModule`], and the imports between
+/// them.
+#[derive(Clone, Debug, Default)]
+pub struct ModuleGraph {
+    /// The modules, in order of registration.
+    pub modules: Vec<GraphModule>,
+
+    /// The interfaces that each module requires or has imported, and their
+    /// providers.
+    pub imports: Vec<GraphImport>,
+}
+
+/// A module in a [`ModuleGraph`].
+#[derive(Clone, Debug)]
+pub struct GraphModule {
+    pub name: &'static str,
+    pub version: Version,
+    pub provides: Vec<InterfaceInfo>,
+    pub state: ModuleState,
+}
+
+/// The state of a module in a [`ModuleGraph`].
+#[derive(Clone, Debug)]
+pub enum ModuleState {
+    /// The module is not loaded, or the graph is of a [`Registry`].
+    NotLoaded,
+    Loading,
+    Loaded,
+    Failed(nxs::Error),
+}
+
+/// An interface required or imported by a module in a [`ModuleGraph`].
+#[derive(Clone, Debug)]
+pub struct GraphImport {
+    /// The name of the module importing `interface`.
+    pub consumer: &'static str,
+
+    pub interface: TypeInfo,
+
+    /// The name of the module from which `interface` was or would be imported,
+    /// or `None` if it has no provider.
+    pub provider: Option<&'static str>,
+
+    /// Whether `consumer` requires `interface` only optionally.
+    pub optional: bool,
+
+    /// Whether `consumer` has imported, or tried to import, `interface` from
+    /// `provider`, rather than only declaring that it requires it.
+    pub imported: bool,
+}
+
+impl ModuleState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::NotLoaded => "not loaded",
+            Self::Loading   => "loading",
+            Self::Loaded    => "loaded",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+impl GraphImport {
+    /// Tells whether `interface` has no provider, although it is required.
+    pub fn is_missing(&self) -> bool {
+        self.provider.is_none() && !self.optional
+    }
+}
+
+/// Returns the name of an interface as shown in a graph, e.g. `TextManager`.
+fn interface_name(interface: &TypeInfo) -> String {
+    interface.short_name().trim_start_matches("dyn ").to_string()
+}
+
+/// Returns `text` as a quoted DOT identifier.
+fn quote(text: &str) -> String {
+    let text = text.replace('\\', "\\\\").replace('"', "\\\"");
+    format!("\"{}\"", text.replace('\n', "\\n"))
+}
+
+impl ModuleGraph {
+    /// Renders the graph in the Graphviz DOT language.
+    ///
+    /// Each module is a node, labelled with the interfaces it provides, and
+    /// each import is an edge from the importer to the provider. Modules that
+    /// failed to load, and required interfaces with no provider, are shown in
+    /// red. Optional imports are dashed, and imports that have actually been
+    /// made are bold.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph modules {\n    node [shape=box];\n");
+        for module in &self.modules {
+            let mut label = format!("{} {}", module.name, module.version);
+            for interface in &module.provides {
+                write!(label, "\nprovides {} {}",
+                       interface_name(&interface.type_info),
+                       interface.version).unwrap();
+            }
+            let style = match &module.state {
+                ModuleState::Failed(error) => {
+                    write!(label, "\nfailed: {}", error).unwrap();
+                    ", color=red, fontcolor=red"
+                }
+                ModuleState::Loading => ", style=dashed",
+                _ => "",
+            };
+            writeln!(dot, "    {} [label={}{}];",
+                     quote(module.name), quote(&label), style).unwrap();
+        }
+        for import in &self.imports {
+            let interface = interface_name(&import.interface);
+            let provider = match import.provider {
+                Some(provider) => quote(provider),
+                None => {
+                    let node = quote(&format!("no provider of {}", interface));
+                    let color = if import.optional { "gray" } else { "red" };
+                    writeln!(dot, "    {} [style=dashed, color={c}, \
+                                   fontcolor={c}];", node, c = color).unwrap();
+                    node
+                }
+            };
+            let mut style = String::new();
+            if import.is_missing() {
+                style.push_str(", color=red, fontcolor=red");
+            }
+            match (import.optional, import.imported) {
+                (true, true)   => style.push_str(", style=\"dashed,bold\""),
+                (true, false)  => style.push_str(", style=dashed"),
+                (false, true)  => style.push_str(", style=bold"),
+                (false, false) => (),
+            }
+            writeln!(dot, "    {} -> {} [label={}{}];", quote(import.consumer),
+                     provider, quote(&interface), style).unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Renders the graph as JSON, in the form:
+    /// ```json
+    /// {
+    ///   "modules": [{
+    ///     "name": "StdTextManager", "version": "0.1.0",
+    ///     "provides": [{ "interface": "TextManager", "version": "0.2.0" }],
+    ///     "state": "failed", "error": "..."
+    ///   }],
+    ///   "imports": [{
+    ///     "consumer": "Commands", "interface": "TextManager",
+    ///     "provider": "StdTextManager", "optional": false, "imported": true,
+    ///     "missing": false
+    ///   }]
+    /// }
+    /// ```
+    /// where `error` is present only for a module that failed to load, and
+    /// `provider` is `null` for an interface with no provider.
+    pub fn to_json(&self) -> String {
+        let modules: Vec<Value> = self.modules.iter().map(|module| {
+            let provides: Vec<Value> = module.provides.iter().map(|i| json!({
+                "interface": interface_name(&i.type_info),
+                "version": i.version.to_string(),
+            })).collect();
+            let mut value = json!({
+                "name": module.name,
+                "version": module.version.to_string(),
+                "provides": provides,
+                "state": module.state.as_str(),
+            });
+            if let ModuleState::Failed(error) = &module.state {
+                value["error"] = json!(error.to_string());
+            }
+            value
+        }).collect();
+        let imports: Vec<Value> = self.imports.iter().map(|import| json!({
+            "consumer": import.consumer,
+            "interface": interface_name(&import.interface),
+            "provider": import.provider,
+            "optional": import.optional,
+            "imported": import.imported,
+            "missing": import.is_missing(),
+        })).collect();
+        let graph = json!({ "modules": modules, "imports": imports });
+        serde_json::to_string_pretty(&graph).unwrap()
+    }
+}
+
+impl Registry {
+    /// Returns the graph of the registered modules, with each required
+    /// interface resolved to the provider from which it would be imported.
+    pub fn graph(&self) -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        for manifest in self.manifests() {
+            graph.modules.push(GraphModule {
+                name: manifest.name,
+                version: manifest.version.clone(),
+                provides: manifest.provides.clone(),
+                state: ModuleState::NotLoaded,
+            });
+            let requires = manifest.requires.iter().map(|i| (i, false));
+            let optional = manifest.optional.iter().map(|i| (i, true));
+            for (interface, optional) in requires.chain(optional) {
+                let interface = interface.type_info;
+                graph.imports.push(GraphImport {
+                    consumer: manifest.name,
+                    interface,
+                    provider: self.provider(interface.id)
+                        .map(|id| self.module(id).manifest.name),
+                    optional,
+                    imported: false,
+                });
+            }
+        }
+        graph
+    }
+}
+
+impl StdRootModule {
+    /// Returns the graph of the registered modules, as by [`Registry::graph`],
+    /// together with the state of each module and the imports each loaded
+    /// module has actually made.
+    pub fn graph(&self) -> ModuleGraph {
+        let modules = self.modules.lock().unwrap();
+        let imports = self.imports.lock().unwrap();
+        let registry = self.registry();
+        let mut graph = registry.graph();
+        // A module with several instances is shown as failed if any failed,
+        // and otherwise as loaded if any is loaded:
+        for (key, instance) in modules.iter() {
+            let state = &mut graph.modules[key.module].state;
+            match (instance.load.peek(), &*state) {
+                (_, ModuleState::Failed(_)) => (),
+                (Some(Err(error)), _) => {
+                    *state = ModuleState::Failed(error.clone());
+                }
+                (_, ModuleState::Loaded) => (),
+                (Some(Ok(_)), _) => *state = ModuleState::Loaded,
+                (None, _) => *state = ModuleState::Loading,
+            }
+        }
+        let mut imports: Vec<_> = imports.iter().collect();
+        imports.sort_by_key(|import| (import.importer, import.provider));
+        for import in imports {
+            let consumer = registry.module(import.importer).manifest.name;
+            let provider = registry.module(import.provider).manifest.name;
+            let edge = graph.imports.iter_mut().find(|edge| {
+                edge.consumer == consumer && edge.interface == import.interface
+                    && (!edge.imported || edge.provider == Some(provider))
+            });
+            match edge {
+                Some(edge) => {
+                    edge.provider = Some(provider);
+                    edge.imported = true;
+                }
+                None => graph.imports.push(GraphImport {
+                    consumer, interface: import.interface,
+                    provider: Some(provider), optional: false, imported: true,
+                }),
+            }
+        }
+        graph
+    }
+}
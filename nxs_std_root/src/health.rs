@@ -0,0 +1,116 @@
+//! The aggregation of the health of the modules loaded by a [`StdRootModule`]
+//! into a status report.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+use nxs_interface::{
+    self as nxs,
+    root::Version,
+    health::{self, HealthState, HealthStatus},
+};
+
+use crate::{StdRootModule, registry::ModuleId};
+
+/// The outcome of [`StdRootModule::status`].
+#[derive(Clone, Debug, Default)]
+pub struct StatusReport {
+    /// The status of each module that has been loaded or is loading, in
+    /// order of registration.
+    pub modules: Vec<ModuleStatus>,
+}
+
+/// The status of one module in a [`StatusReport`].
+#[derive(Clone, Debug)]
+pub struct ModuleStatus {
+    pub name: &'static str,
+    pub version: Version,
+
+    /// The health of the module, as it reported, or as inferred by the root
+    /// if the module does not implement [`Health`](health::Health). Of a
+    /// module with several instances, this is the health of the worst.
+    pub health: HealthStatus,
+}
+
+impl StatusReport {
+    /// Returns the health of the worst module, or
+    /// [`Healthy`](HealthState::Healthy) if there are none.
+    pub fn overall(&self) -> HealthState {
+        self.modules.iter().map(|module| module.health.state).max()
+            .unwrap_or(HealthState::Healthy)
+    }
+
+    /// Tells whether every module is healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.overall() == HealthState::Healthy
+    }
+}
+
+impl fmt::Display for StatusReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = |state| {
+            self.modules.iter().filter(|m| m.health.state == state).count()
+        };
+        write!(f, "{}: {} modules, {} degraded, {} failed", self.overall(),
+               self.modules.len(), count(HealthState::Degraded),
+               count(HealthState::Failed))?;
+        for module in &self.modules {
+            write!(f, "\n{}", module)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ModuleStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "module `{}` {} is {}", self.name, self.version, self.health)
+    }
+}
+
+impl StdRootModule {
+    /// Returns the health of each module that has been loaded or is loading.
+    ///
+    /// A module that implements [`Health`](health::Health) is asked for its
+    /// health, and is reported as failed if this panics. Any other module is
+    /// reported as healthy once loaded. A module still loading is reported as
+    /// degraded, and one that failed to load as failed.
+    pub fn status(&self) -> StatusReport {
+        // The modules are checked without holding the lock, so that they may
+        // use the root while doing so:
+        let instances: Vec<_> = self.modules.lock().unwrap().iter()
+            .map(|(key, instance)| (key.module, instance.load.peek().cloned()))
+            .collect();
+        let mut worst: BTreeMap<ModuleId, HealthStatus> = BTreeMap::new();
+        for (id, load) in instances {
+            let health = match load {
+                None => HealthStatus::degraded("still loading"),
+                Some(Err(error)) => HealthStatus::failed(error.to_string()),
+                Some(Ok(module)) => {
+                    let check = AssertUnwindSafe(|| health::health_of(module));
+                    match panic::catch_unwind(check) {
+                        Ok(health) => health.unwrap_or_else(|| {
+                            HealthStatus::healthy("")
+                        }),
+                        Err(panic) => {
+                            let error = nxs::Error::panicked(panic);
+                            HealthStatus::failed(error.to_string())
+                        }
+                    }
+                }
+            };
+            match worst.get(&id) {
+                Some(other) if other.state >= health.state => (),
+                _ => { worst.insert(id, health); }
+            }
+        }
+        let registry = self.registry();
+        let modules = worst.into_iter().map(|(id, health)| {
+            let manifest = &registry.module(id).manifest;
+            ModuleStatus {
+                name: manifest.name, version: manifest.version.clone(), health,
+            }
+        }).collect();
+        StatusReport { modules }
+    }
+}
@@ -0,0 +1,200 @@
+//! Tracking of the imports made by each leaf module.
+
+use std::sync::{Arc, Mutex};
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    util::dyn_cast::DynCast,
+    root::{RootModule, InterfaceInfo, DynHandle, Liveness},
+    logging::Logger,
+    task::Spawner,
+};
+use futures::future::BoxFuture;
+
+use crate::{
+    StdRootModule, InstanceKey, ScopeKey, loading::Timing,
+    logging::ModuleLogger, registry::ModuleId, task::ModuleSpawner,
+};
+
+/// The proxy of a [`StdRootModule`] given to a leaf module when it is loaded.
+///
+/// Imports made through an `Importer` are attributed to that module, and the
+/// time spent waiting for them is recorded in the timing of its load.
+#[derive(DynCast)]
+#[dyn_cast(base_traits(RootModule))]
+pub struct Importer {
+    root: &'static StdRootModule,
+    key: InstanceKey,
+    liveness: Liveness,
+    timing: Arc<Mutex<Timing>>,
+    logger: ModuleLogger,
+    spawner: ModuleSpawner,
+}
+
+impl Importer {
+    /// Constructs the proxy for the instance `key` of a module, with the given
+    /// liveness, timing, logger and spawner.
+    pub(crate) fn new(
+        root: &'static StdRootModule, key: InstanceKey, liveness: Liveness,
+        timing: Arc<Mutex<Timing>>, logger: ModuleLogger,
+        spawner: ModuleSpawner,
+    ) -> Self {
+        Self { root, key, liveness, timing, logger, spawner }
+    }
+
+    /// Returns the module to which this proxy was given.
+    pub fn module(&self) -> ModuleId {
+        self.key.module
+    }
+
+    /// Returns the instance of the module to which this proxy was given.
+    pub(crate) fn key(&self) -> InstanceKey {
+        self.key
+    }
+
+    /// Returns the timing of the load of the module.
+    pub(crate) fn timing(&self) -> &Mutex<Timing> {
+        &self.timing
+    }
+
+    /// Returns the root of which this is a proxy.
+    pub fn root(&self) -> &'static StdRootModule {
+        self.root
+    }
+}
+
+impl RootModule for Importer {
+    fn dyn_import(&'static self, interface: InterfaceInfo)
+    -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        self.root.import_by(Some(self), interface)
+    }
+
+    fn dyn_import_all(&'static self, interface: InterfaceInfo)
+    -> BoxFuture<'static, nxs::Result<Vec<DynHandle>>> {
+        self.root.import_all_by(Some(self), interface)
+    }
+
+    fn dyn_import_named(&'static self, interface: InterfaceInfo, name: &str)
+    -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        self.root.import_named_by(Some(self), interface, name)
+    }
+
+    fn instance(&self) -> Option<&'static str> {
+        let registry = self.root.registry();
+        let module = registry.module(self.key.module);
+        module.base.map(|_| module.manifest.name)
+    }
+
+    fn importer(&self) -> Option<&'static str> {
+        let registry = self.root.registry();
+        Some(registry.module(self.key.scope.importer()?).manifest.name)
+    }
+
+    fn liveness(&self) -> Option<Liveness> {
+        Some(self.liveness.clone())
+    }
+
+    fn logger(&self) -> &dyn Logger {
+        &self.logger
+    }
+
+    fn spawner(&self) -> &dyn Spawner {
+        &self.spawner
+    }
+}
+
+/// The set of loads currently waiting for other loads to complete.
+///
+/// Each edge means that one instance of a module is waiting for another to
+/// load, because it is importing a given interface provided by the other. Edges
+/// may be repeated, if an instance performs several concurrent imports of the
+/// same interface.
+///
+/// The transient instances of a module loaded for the same importer are taken
+/// to be one, as each import makes a new one: otherwise a transient module
+/// importing itself would load instances endlessly rather than form a cycle.
+#[derive(Default)]
+pub struct WaitGraph {
+    edges: Vec<Edge>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Edge {
+    from: InstanceKey,
+    to: InstanceKey,
+    via: TypeInfo,
+}
+
+impl WaitGraph {
+    /// Records that `from` is waiting for `to` to load, as it is importing
+    /// the interface `via`, until the returned guard is dropped.
+    ///
+    /// If `to` is already waiting, directly or indirectly, for `from`, nothing
+    /// is recorded, and instead an [`nxs::Error::Cycle`] is returned.
+    pub fn wait(
+        graph: &'static Mutex<WaitGraph>,
+        from: InstanceKey, to: InstanceKey, via: TypeInfo,
+    ) -> nxs::Result<WaitGuard> {
+        let (from, to) = (node(from), node(to));
+        let mut waits = graph.lock().unwrap();
+        if let Some(path) = waits.path(to, from) {
+            // The cycle of interfaces starts and ends with the one by which
+            // `from` is reached, which is `via` itself if `from == to`:
+            let last = path.last().copied().unwrap_or(via);
+            let path = vec![last, via].into_iter().chain(path).collect();
+            return Err(nxs::Error::Cycle { path });
+        }
+        let edge = Edge { from, to, via };
+        waits.edges.push(edge);
+        Ok(WaitGuard { graph, edge })
+    }
+
+    /// Returns the interfaces along a path of waits from `from` to `to`, if
+    /// one exists.
+    fn path(&self, from: InstanceKey, to: InstanceKey)
+    -> Option<Vec<TypeInfo>> {
+        let mut path = Vec::new();
+        let mut visited = vec![from];
+        self.search(from, to, &mut path, &mut visited).then_some(path)
+    }
+
+    fn search(
+        &self, from: InstanceKey, to: InstanceKey,
+        path: &mut Vec<TypeInfo>, visited: &mut Vec<InstanceKey>,
+    ) -> bool {
+        if from == to { return true; }
+        for edge in &self.edges {
+            if edge.from != from || visited.contains(&edge.to) { continue; }
+            visited.push(edge.to);
+            path.push(edge.via);
+            if self.search(edge.to, to, path, visited) { return true; }
+            path.pop();
+        }
+        false
+    }
+}
+
+/// Returns the node of a [`WaitGraph`] representing the instance `key`.
+fn node(key: InstanceKey) -> InstanceKey {
+    match key.scope {
+        ScopeKey::Transient(importer, _) => InstanceKey {
+            scope: ScopeKey::Transient(importer, 0), ..key
+        },
+        _ => key,
+    }
+}
+
+/// Removes a wait from a [`WaitGraph`] when dropped.
+pub struct WaitGuard {
+    graph: &'static Mutex<WaitGraph>,
+    edge: Edge,
+}
+
+impl Drop for WaitGuard {
+    fn drop(&mut self) {
+        let mut waits = self.graph.lock().unwrap();
+        if let Some(i) = waits.edges.iter().position(|&e| e == self.edge) {
+            waits.edges.remove(i);
+        }
+    }
+}
@@ -0,0 +1,587 @@
+//! The standard implementation of [`RootModule`].
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    util::dyn_cast::{DynCast, DynCastExt},
+    root::{
+        RootModule, LeafModule, LoadFn, Manifest, InterfaceInfo, DynHandle,
+        Liveness, Scope,
+    },
+    logging::Logger,
+};
+use futures::future::{BoxFuture, FutureExt, Shared};
+
+mod config;
+mod graph;
+mod health;
+mod importer;
+mod loading;
+mod logging;
+mod metrics;
+mod plugin;
+mod registry;
+mod shutdown;
+mod task;
+mod tests;
+
+use importer::{Importer, WaitGraph};
+use config::ConfigStore;
+use loading::{Timing, timed_load};
+use logging::{LogRouter, ModuleLogger};
+use metrics::MetricStore;
+use task::{Executor, ModuleSpawner, TaskSet};
+use registry::ModuleId;
+pub use registry::Registry;
+pub use config::{Config, RootConfig, MetricsTable, ConfigError, StdConfig};
+pub use graph::{ModuleGraph, GraphModule, GraphImport, ModuleState};
+pub use health::{StatusReport, ModuleStatus};
+pub use loading::{LoadConfig, LoadReport, ModuleTiming, Wait};
+pub use logging::{LogConfig, LogSink, LogFormat};
+pub use metrics::{MetricsConfig, StdMetrics};
+pub use plugin::{PluginError, PluginResult};
+pub use shutdown::{ShutdownConfig, ShutdownReport, ShutdownFailure};
+
+/// A root module which loads leaf modules on demand from a [`Registry`].
+///
+/// Each leaf module is loaded the first time any interface it provides is
+/// imported, and is subsequently kept until it is unloaded, so that the same
+/// instance is shared between all importers of all its interfaces.
+///
+/// Loading is *single-flight*: if several importers request the same module
+/// concurrently, it is loaded only once, and every importer receives the same
+/// result, whether it is the loaded module or the error that caused loading to
+/// fail.
+///
+/// Each module is loaded with its own proxy of the root, through which the
+/// root knows which loads are waiting for which others. An import that would
+/// cause a load to wait, directly or indirectly, for itself fails with an
+/// error naming the cycle of interfaces involved, rather than deadlocking.
+/// The proxy also enforces any [restriction](Registry::restrict) on the
+/// interfaces that the module may import.
+///
+/// The [`load_modules`](Self::load_modules) method loads several modules
+/// concurrently, each within a configurable time, and the time taken by each
+/// load, including the time spent waiting for each import, is available from
+/// [`load_report`](Self::load_report).
+///
+/// A panic while loading a module is caught, and becomes the error with which
+/// the load fails, so that the module is left failed until it is unloaded,
+/// without affecting any module that did not import from it. Likewise, a
+/// panic while shutting down a module is reported as a failure to shut down.
+///
+/// A module may be registered as several named instances, each of which is
+/// loaded separately, with its own proxy of the root, and is imported by name
+/// through [`RootModule::dyn_import_named`].
+///
+/// A module whose manifest declares a [`Scope`] other than the default is not
+/// shared in this way: a separate instance is loaded for each module that
+/// imports it, or for every import, and is told the name of the importer by
+/// [`RootModule::importer`]. Every instance of a module is unloaded with it.
+///
+/// A module may be [unloaded](Self::unload), which also unloads every module
+/// that imported from it, directly or indirectly, and invalidates all
+/// [`Handle`](nxs::root::Handle)s to them. The next import of an unloaded
+/// module loads it again, possibly from a [replacement](Self::replace) build.
+/// The memory of unloaded instances is never freed, since references obtained
+/// from their handles may still exist.
+#[derive(DynCast)]
+#[dyn_cast(base_traits(RootModule))]
+pub struct StdRootModule {
+    registry: RwLock<Registry>,
+    modules: Mutex<HashMap<InstanceKey, Instance>>,
+    imports: Mutex<HashSet<Import>>,
+    waits: Mutex<WaitGraph>,
+    config: ConfigStore,
+    logs: LogRouter,
+    metrics: MetricStore,
+    executor: RwLock<Arc<Executor>>,
+    transients: AtomicUsize,
+}
+
+/// The (possibly already completed) loading of a leaf module, shared between
+/// all of its importers.
+type LoadFuture = Shared<BoxFuture<'static, nxs::Result<&'static dyn LeafModule>>>;
+
+/// An instance of a leaf module, which has been or is being loaded.
+struct Instance {
+    load: LoadFuture,
+    liveness: Liveness,
+    timing: Arc<Mutex<Timing>>,
+    tasks: Arc<TaskSet>,
+}
+
+impl Instance {
+    /// Marks the instance as unloaded, invalidating all handles to it and
+    /// cancelling its background tasks.
+    fn unload(&self) {
+        self.liveness.invalidate();
+        self.tasks.cancel();
+    }
+}
+
+/// Identifies one of the instances of a module, according to its [`Scope`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct InstanceKey {
+    module: ModuleId,
+    scope: ScopeKey,
+}
+
+/// The part of an [`InstanceKey`] distinguishing the instances of one module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum ScopeKey {
+    /// The instance of a [`Scope::Singleton`] module.
+    Shared,
+
+    /// The instance of a [`Scope::PerImporter`] module loaded for the given
+    /// module, or for the root itself if `None`.
+    Importer(Option<ModuleId>),
+
+    /// An instance of a [`Scope::Transient`] module, loaded for the given
+    /// module or the root, with a serial number unique to the root.
+    Transient(Option<ModuleId>, usize),
+}
+
+impl ScopeKey {
+    /// Returns the module for which the instance is loaded, if it is not
+    /// shared.
+    fn importer(self) -> Option<ModuleId> {
+        match self {
+            Self::Shared => None,
+            Self::Importer(importer) | Self::Transient(importer, _) => importer,
+        }
+    }
+}
+
+/// A record that the module `importer` imported the interface `interface`
+/// provided by the module `provider`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+struct Import {
+    importer: ModuleId,
+    provider: ModuleId,
+    interface: TypeInfo,
+}
+
+impl StdRootModule {
+    pub fn new(registry: Registry) -> Self {
+        Self::with_config(registry, Config::default())
+    }
+
+    /// Constructs a root with the given configuration, which is available to
+    /// leaf modules if [`StdConfig`] is registered.
+    pub fn with_config(registry: Registry, config: Config) -> Self {
+        Self {
+            registry: RwLock::new(registry), modules: Mutex::default(),
+            imports: Mutex::default(), waits: Mutex::default(),
+            logs: LogRouter::new(&config.logging()),
+            metrics: MetricStore::default(),
+            executor: RwLock::new(Arc::new(task::spawn_thread)),
+            config: ConfigStore::new(config),
+            transients: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the registry from which this root loads modules.
+    pub fn registry(&self) -> RwLockReadGuard<'_, Registry> {
+        self.registry.read().unwrap()
+    }
+
+    /// Returns the manifests of all modules that have been loaded successfully.
+    pub fn loaded(&self) -> Vec<Manifest> {
+        let modules = self.modules.lock().unwrap();
+        let registry = self.registry();
+        let mut ids: Vec<ModuleId> = modules.iter()
+            .filter(|(_, instance)| matches!(instance.load.peek(), Some(Ok(_))))
+            .map(|(key, _)| key.module).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        ids.into_iter().map(|id| registry.module(id).manifest.clone()).collect()
+    }
+
+    /// Loads the module named `name`, if it is not already loaded.
+    pub async fn load_module(&'static self, name: &str) -> nxs::Result<()> {
+        let id = self.find(name)?;
+        self.load(self.key(id, None)).0.await.map(|_| ())
+    }
+
+    /// Unloads the module named `name`, if it is loaded, together with every
+    /// module that imported from it, directly or indirectly.
+    ///
+    /// Each module is first shut down, before any module that it imported
+    /// from, and given the time configured for it to do so, as by
+    /// [`shutdown`](Self::shutdown). A module that fails to stop cleanly is
+    /// logged, and unloaded regardless.
+    ///
+    /// Returns the names of the unloaded modules, with each module preceding
+    /// those it imported from.
+    pub async fn unload(&self, name: &str) -> nxs::Result<Vec<&'static str>> {
+        let unloaded = self.unload_by_id(self.find(name)?).await;
+        let registry = self.registry();
+        Ok(unloaded.into_iter()
+            .map(|id| registry.module(id).manifest.name).collect())
+    }
+
+    /// Unloads the module named `name` as by [`unload`](Self::unload), and
+    /// then loads again each module that was unloaded.
+    ///
+    /// Returns the names of the reloaded modules, or the first error that
+    /// occurred while reloading them.
+    pub async fn reload(&'static self, name: &str)
+    -> nxs::Result<Vec<&'static str>> {
+        let id = self.find(name)?;
+        self.reload_by_id(id, None).await
+    }
+
+    /// Unloads the module named `name` as by [`unload`](Self::unload), and
+    /// replaces it in the registry with a module having the given manifest,
+    /// which is loaded by `load`. Each module that was unloaded is then loaded
+    /// again, as by [`reload`](Self::reload).
+    pub async fn replace(
+        &'static self, name: &str, manifest: Manifest, load: LoadFn,
+    ) -> nxs::Result<Vec<&'static str>> {
+        let id = self.find(name)?;
+        self.reload_by_id(id, Some((manifest, load))).await
+    }
+
+    /// Imports the interface `interface` on behalf of the module `importer`, or
+    /// of the root itself if `importer` is `None`, from the first of its
+    /// providers that loads successfully.
+    fn import_by(
+        &'static self, importer: Option<&'static Importer>,
+        interface: InterfaceInfo,
+    ) -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        let as_type = interface.type_info;
+        let candidates = check_access(&self.registry(), importer, as_type)
+            .and_then(|()| self.candidates(&interface, false));
+        Box::pin(async move {
+            let mut first_error = None;
+            for provider in candidates? {
+                let result = self.import_provider(importer, provider, as_type)
+                    .await.and_then(|(module, liveness)| {
+                        Self::handle(module, liveness, as_type)
+                    });
+                match result {
+                    Ok(handle) => return Ok(handle),
+                    Err(error) => { first_error.get_or_insert(error); }
+                }
+            }
+            Err(first_error.expect("There must be at least one candidate."))
+        })
+    }
+
+    /// Imports the interface `interface` from each of its compatible
+    /// providers, on behalf of the module `importer`, or of the root itself if
+    /// `importer` is `None`.
+    ///
+    /// Yields no handles if there are no providers, but fails if there are
+    /// providers and none of them is compatible.
+    fn import_all_by(
+        &'static self, importer: Option<&'static Importer>,
+        interface: InterfaceInfo,
+    ) -> BoxFuture<'static, nxs::Result<Vec<DynHandle>>> {
+        let as_type = interface.type_info;
+        let candidates = check_access(&self.registry(), importer, as_type)
+            .and_then(|()| match self.candidates(&interface, true) {
+                Err(nxs::Error::NotProvided { .. }) => Ok(Vec::new()),
+                result => result,
+            });
+        Box::pin(async move {
+            let candidates = candidates?;
+            let mut modules = Vec::with_capacity(candidates.len());
+            for provider in candidates {
+                modules.push(self.import_provider(importer, provider, as_type).await?);
+            }
+            modules.into_iter().map(|(module, liveness)| {
+                Self::handle(module, liveness, as_type)
+            }).collect()
+        })
+    }
+
+    /// Imports the interface `interface` from the named instance `name`, on
+    /// behalf of the module `importer`, or of the root itself if `importer` is
+    /// `None`.
+    fn import_named_by(
+        &'static self, importer: Option<&'static Importer>,
+        interface: InterfaceInfo, name: &str,
+    ) -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        let as_type = interface.type_info;
+        let registry = self.registry();
+        let provider = check_access(&registry, importer, as_type).and_then(|()| {
+            registry.instance(as_type.id, name).ok_or_else(|| {
+                let name = name.to_string();
+                nxs::Error::NoInstance { interface: as_type, name }
+            })
+        }).and_then(|id| {
+            check_version(&registry, id, &interface).map(|()| id)
+        });
+        drop(registry);
+        Box::pin(async move {
+            let (module, liveness)
+                = self.import_provider(importer, provider?, as_type).await?;
+            Self::handle(module, liveness, as_type)
+        })
+    }
+
+    /// Imports the module `provider` for its interface `as_type`, on behalf of
+    /// the module `importer`, or of the root itself if `importer` is `None`.
+    async fn import_provider(
+        &'static self, importer: Option<&'static Importer>, provider: ModuleId,
+        as_type: TypeInfo,
+    ) -> nxs::Result<(&'static dyn LeafModule, Liveness)> {
+        let key = self.key(provider, importer.map(Importer::module));
+        let (load, liveness) = self.load(key);
+        if let Some(importer) = importer {
+            let importer = importer.module();
+            let import = Import { importer, provider, interface: as_type };
+            self.imports.lock().unwrap().insert(import);
+        }
+        let name = self.registry().module(provider).manifest.name;
+        let result = match (importer, load.peek()) {
+            (_, Some(result)) => result.clone(),
+            (None, None) => load.await,
+            (Some(importer), None) => {
+                let _wait = WaitGraph::wait(
+                    &self.waits, importer.key(), key, as_type,
+                )?;
+                let started = Instant::now();
+                let result = load.await;
+                importer.timing().lock().unwrap()
+                    .wait(as_type, name, started.elapsed());
+                result
+            }
+        };
+        self.metrics.record_import(as_type, name, result.is_ok());
+        Ok((result?, liveness))
+    }
+
+    /// Returns the key of the instance of the module `id` to be imported by
+    /// the module `importer`, or by the root itself if `importer` is `None`,
+    /// according to the scope of the module.
+    fn key(&self, id: ModuleId, importer: Option<ModuleId>) -> InstanceKey {
+        let scope = match self.registry().module(id).manifest.scope {
+            Scope::Singleton   => ScopeKey::Shared,
+            Scope::PerImporter => ScopeKey::Importer(importer),
+            Scope::Transient   => ScopeKey::Transient(
+                importer, self.transients.fetch_add(1, Ordering::Relaxed),
+            ),
+        };
+        InstanceKey { module: id, scope }
+    }
+
+    /// Returns a handle to `module` yielding its interface `as_type`.
+    fn handle(
+        module: &'static dyn LeafModule, liveness: Liveness, as_type: TypeInfo,
+    ) -> nxs::Result<DynHandle> {
+        let module_ref = module.dyn_cast_ref(as_type.id).ok_or_else(|| {
+            nxs::Error::NotCastable {
+                interface: as_type, castable: module.castable_type_infos(),
+            }
+        })?;
+        Ok(DynHandle { module: module_ref, liveness })
+    }
+
+    /// Returns the registered providers of `interface` which implement a
+    /// compatible version of it, in the order in which they should be tried,
+    /// ignoring any selected provider if `all` is true.
+    fn candidates(&self, interface: &InterfaceInfo, all: bool)
+    -> nxs::Result<Vec<ModuleId>> {
+        let registry = self.registry();
+        let as_type = interface.type_info;
+        let ids = match all {
+            true  => registry.preferred(as_type.id),
+            false => registry.candidates(as_type.id),
+        };
+        let mut incompatible = None;
+        let compatible: Vec<ModuleId> = ids.into_iter().filter(|&id| {
+            match check_version(&registry, id, interface) {
+                Ok(()) => true,
+                Err(error) => { incompatible.get_or_insert(error); false }
+            }
+        }).collect();
+        match (compatible.is_empty(), incompatible) {
+            (false, _)          => Ok(compatible),
+            (true, Some(error)) => Err(error),
+            (true, None)        => Err(nxs::Error::NotProvided { interface: as_type }),
+        }
+    }
+
+    /// Returns the first registered module named `name`.
+    fn find(&self, name: &str) -> nxs::Result<ModuleId> {
+        self.registry().find(name).ok_or_else(|| {
+            nxs::Error::UnknownModule { name: name.to_string() }
+        })
+    }
+
+    /// Returns the loading of the current instance `key` of a module,
+    /// starting it if this has not already happened, and its liveness.
+    fn load(&'static self, key: InstanceKey) -> (LoadFuture, Liveness) {
+        let mut modules = self.modules.lock().unwrap();
+        if let Some(instance) = modules.get(&key) {
+            return (instance.load.clone(), instance.liveness.clone());
+        }
+        let registry = self.registry();
+        let module = registry.module(key.module);
+        let (load, name) = (module.load, module.manifest.name);
+        let logger = ModuleLogger::new(self, name, module.manifest.version.clone());
+        drop(registry);
+        let timeout = self.config().loading().timeout_for(name);
+        let (liveness, timing) = (Liveness::new(), Arc::default());
+        let tasks = Arc::default();
+        let spawner = ModuleSpawner::new(
+            self, name, logger.clone(), Arc::clone(&tasks),
+        );
+        let importer: &'static Importer = Box::leak(Box::new(Importer::new(
+            self, key, liveness.clone(), Arc::clone(&timing), logger, spawner,
+        )));
+        let loading = timed_load(
+            load, importer, name, timeout, Arc::clone(&timing),
+        );
+        let future = async move {
+            let started = Instant::now();
+            let result = loading.await;
+            self.metrics.record_load(name, started.elapsed(), result.is_ok());
+            // A transient instance is used only by the import that loaded it,
+            // so it is not kept, lest every import add to those tracked:
+            if let ScopeKey::Transient(..) = key.scope {
+                self.modules.lock().unwrap().remove(&key);
+            }
+            let module: &'static dyn LeafModule = Box::leak(result?);
+            Ok(module)
+        }.boxed().shared();
+        modules.insert(key, Instance {
+            load: future.clone(), liveness: liveness.clone(), timing, tasks,
+        });
+        (future, liveness)
+    }
+
+    /// Unloads the module `id` and all modules that imported from it, directly
+    /// or indirectly, along with the instances loaded on their behalf,
+    /// returning those modules that were loaded, with each module preceding
+    /// those it imported from.
+    async fn unload_by_id(&self, id: ModuleId) -> Vec<ModuleId> {
+        let (order, instances) = {
+            let mut modules = self.modules.lock().unwrap();
+            let mut imports = self.imports.lock().unwrap();
+            let (mut order, mut instances) = (Vec::new(), Vec::new());
+            importers_first(&imports, id, &mut HashSet::new(), &mut order);
+            order.retain(|&id| {
+                let keys: Vec<InstanceKey> = modules.keys()
+                    .filter(|key| key.module == id).copied().collect();
+                for key in &keys {
+                    instances.push((id, modules.remove(key).unwrap()));
+                }
+                !keys.is_empty()
+            });
+            // The instances loaded on behalf of an unloaded module serve no
+            // other, so they are stopped too, after the module itself:
+            let owned: Vec<InstanceKey> = modules.keys()
+                .filter(|key| {
+                    key.scope.importer().is_some_and(|id| order.contains(&id))
+                })
+                .copied().collect();
+            for key in owned {
+                instances.push((key.module, modules.remove(&key).unwrap()));
+            }
+            imports.retain(|import| !order.contains(&import.importer));
+            (order, instances)
+        };
+        let config = self.config().shutdown();
+        for (id, instance) in instances {
+            if let Some((name, Err(failure))) =
+                self.stop(id, instance, &config).await
+            {
+                nxs::nxs_error!(&self.logs, "module `{}` {}", name, failure);
+            }
+        }
+        order
+    }
+
+    /// Unloads the module `id`, optionally replaces it in the registry, and
+    /// loads again each module that was unloaded.
+    async fn reload_by_id(
+        &'static self, id: ModuleId, replacement: Option<(Manifest, LoadFn)>,
+    ) -> nxs::Result<Vec<&'static str>> {
+        let unloaded = self.unload_by_id(id).await;
+        if let Some((manifest, load)) = replacement {
+            self.registry.write().unwrap().replace(id, manifest, load);
+        }
+        let mut names = Vec::with_capacity(unloaded.len());
+        for &id in unloaded.iter().rev() {
+            self.load(self.key(id, None)).0.await?;
+            names.push(self.registry().module(id).manifest.name);
+        }
+        names.reverse();
+        Ok(names)
+    }
+}
+
+/// Checks that the module `importer`, if any, is allowed to import the
+/// interface `as_type`.
+fn check_access(
+    registry: &Registry, importer: Option<&Importer>, as_type: TypeInfo,
+) -> nxs::Result<()> {
+    match importer.map(Importer::module) {
+        Some(id) if !registry.may_import(id, &as_type) => {
+            Err(nxs::Error::AccessDenied {
+                module: registry.module(id).manifest.name, interface: as_type,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the module `id` provides a version of `interface` compatible
+/// with that required.
+fn check_version(registry: &Registry, id: ModuleId, interface: &InterfaceInfo)
+-> nxs::Result<()> {
+    let as_type = interface.type_info;
+    let manifest = &registry.module(id).manifest;
+    let provided = manifest.provides.iter()
+        .find(|provided| provided.type_info == as_type)
+        .expect("A provider must list its interface in its manifest.");
+    if interface.is_compatible(&provided.version) { return Ok(()); }
+    Err(nxs::Error::IncompatibleVersion {
+        interface: as_type, module: manifest.name,
+        required: Box::new(interface.version.clone()),
+        provided: Box::new(provided.version.clone()),
+    })
+}
+
+/// Appends to `order` the module `id` and each module that imported from it,
+/// directly or indirectly, unless already `visited`, with each module preceding
+/// those it imported from.
+fn importers_first(
+    imports: &HashSet<Import>, id: ModuleId,
+    visited: &mut HashSet<ModuleId>, order: &mut Vec<ModuleId>,
+) {
+    if !visited.insert(id) { return; }
+    for import in imports.iter().filter(|i| i.provider == id) {
+        importers_first(imports, import.importer, visited, order);
+    }
+    order.push(id);
+}
+
+impl RootModule for StdRootModule {
+    fn dyn_import(&'static self, interface: InterfaceInfo)
+    -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        self.import_by(None, interface)
+    }
+
+    fn dyn_import_all(&'static self, interface: InterfaceInfo)
+    -> BoxFuture<'static, nxs::Result<Vec<DynHandle>>> {
+        self.import_all_by(None, interface)
+    }
+
+    fn dyn_import_named(&'static self, interface: InterfaceInfo, name: &str)
+    -> BoxFuture<'static, nxs::Result<DynHandle>> {
+        self.import_named_by(None, interface, name)
+    }
+
+    fn logger(&self) -> &dyn Logger {
+        &self.logs
+    }
+}
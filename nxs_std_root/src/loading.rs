@@ -0,0 +1,225 @@
+//! Concurrent loading of the modules of a [`StdRootModule`], and the timing of
+//! each load.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    root::{LeafModule, LoadFn, RootModule},
+};
+use futures::future::{self, Either, FutureExt};
+use futures_timer::Delay;
+
+use crate::{StdRootModule, registry::ModuleId};
+
+/// The time allowed for each module to load, unless configured otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The time allowed for modules to load.
+#[derive(Clone, Debug)]
+pub struct LoadConfig {
+    /// The time allowed for each module not listed in `module_timeouts`.
+    pub timeout: Duration,
+
+    /// The time allowed for each module, by name.
+    pub module_timeouts: HashMap<String, Duration>,
+}
+
+impl Default for LoadConfig {
+    fn default() -> Self {
+        Self { timeout: DEFAULT_TIMEOUT, module_timeouts: HashMap::new() }
+    }
+}
+
+impl LoadConfig {
+    /// Returns the time allowed for the module named `name` to load.
+    pub fn timeout_for(&self, name: &str) -> Duration {
+        self.module_timeouts.get(name).copied().unwrap_or(self.timeout)
+    }
+}
+
+/// The outcome of [`StdRootModule::load_report`].
+#[derive(Clone, Debug, Default)]
+pub struct LoadReport {
+    /// The timing of each module that has started loading, in the order in
+    /// which they started.
+    pub modules: Vec<ModuleTiming>,
+}
+
+/// The time taken by one module to load.
+#[derive(Clone, Debug)]
+pub struct ModuleTiming {
+    pub name: &'static str,
+
+    /// The time at which the module started loading, relative to the first
+    /// module in the report.
+    pub started: Duration,
+
+    /// The time from when the module started loading until it finished, or
+    /// `None` if it is still loading.
+    pub elapsed: Option<Duration>,
+
+    /// Whether the module failed to load.
+    pub failed: bool,
+
+    /// The imports for which the module waited for another module to load,
+    /// in the order in which they finished.
+    pub waits: Vec<Wait>,
+}
+
+/// A wait by a loading module for another module to load.
+#[derive(Clone, Debug)]
+pub struct Wait {
+    /// The interface being imported.
+    pub interface: TypeInfo,
+
+    /// The name of the module providing `interface`.
+    pub provider: &'static str,
+
+    /// The time spent waiting.
+    pub waited: Duration,
+}
+
+impl LoadReport {
+    /// Returns the time from when the first module started loading until the
+    /// last finished.
+    pub fn total(&self) -> Duration {
+        self.modules.iter()
+            .filter_map(|module| Some(module.started + module.elapsed?))
+            .max().unwrap_or_default()
+    }
+}
+
+impl ModuleTiming {
+    /// Returns the time spent loading other than waiting for imports, or
+    /// `None` if the module is still loading.
+    ///
+    /// Waits for concurrent imports are counted separately, so this may be
+    /// an underestimate.
+    pub fn own_time(&self) -> Option<Duration> {
+        let waited = self.waits.iter().map(|wait| wait.waited).sum();
+        Some(self.elapsed?.saturating_sub(waited))
+    }
+}
+
+impl fmt::Display for LoadReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} modules loaded in {:?}",
+               self.modules.len(), self.total())?;
+        for module in &self.modules {
+            write!(f, "\n{}", module)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ModuleTiming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.elapsed, self.failed) {
+            (None, _) => write!(f, "module `{}` is still loading", self.name)?,
+            (Some(elapsed), false) => {
+                write!(f, "module `{}` loaded in {:?}", self.name, elapsed)?
+            }
+            (Some(elapsed), true) => {
+                write!(f, "module `{}` failed after {:?}", self.name, elapsed)?
+            }
+        }
+        for wait in &self.waits {
+            write!(f, "\n  waited {:?} for `{}` from module `{}`", wait.waited,
+                   wait.interface.short_name(), wait.provider)?;
+        }
+        Ok(())
+    }
+}
+
+/// The timing of one instance of a module, as it is recorded.
+#[derive(Default)]
+pub(crate) struct Timing {
+    started: Option<Instant>,
+    elapsed: Option<Duration>,
+    failed: bool,
+    waits: Vec<Wait>,
+}
+
+impl Timing {
+    pub fn wait(&mut self, interface: TypeInfo, provider: &'static str,
+                waited: Duration) {
+        self.waits.push(Wait { interface, provider, waited });
+    }
+}
+
+/// Loads a module named `name` using `load`, with the given root, recording
+/// its timing in `timing`.
+///
+/// The load fails with [`nxs::Error::TimedOut`] if it does not finish within
+/// `timeout`, or with [`nxs::Error::Panicked`] if it panics.
+pub(crate) async fn timed_load(
+    load: LoadFn, root: &'static dyn RootModule, name: &'static str,
+    timeout: Duration, timing: Arc<Mutex<Timing>>,
+) -> nxs::Result<Box<dyn LeafModule>> {
+    let started = Instant::now();
+    timing.lock().unwrap().started = Some(started);
+    let loading = AssertUnwindSafe(async move { load(root).await })
+        .catch_unwind().map(|result| result.unwrap_or_else(|panic| {
+            Err(nxs::Error::panicked(panic).load_failed(name))
+        }));
+    let result = future::select(loading.boxed(), Delay::new(timeout));
+    let result = match result.await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => {
+            Err(nxs::Error::TimedOut { timeout }.load_failed(name))
+        }
+    };
+    let mut timing = timing.lock().unwrap();
+    timing.elapsed = Some(started.elapsed());
+    timing.failed = result.is_err();
+    result
+}
+
+impl StdRootModule {
+    /// Loads the modules with the given names concurrently, together with any
+    /// modules they import, so that modules not depending on each other are
+    /// loaded at the same time.
+    ///
+    /// Returns the first error, in the order of `names`, if any module failed
+    /// to load. Each module is allowed the time configured by
+    /// [`Config::loading`](crate::Config::loading) to load, including the time
+    /// spent waiting for its imports.
+    pub async fn load_modules(&'static self, names: &[&str])
+    -> nxs::Result<()> {
+        let ids = names.iter().map(|name| self.find(name))
+            .collect::<nxs::Result<Vec<ModuleId>>>()?;
+        let loads = ids.into_iter().map(|id| self.load(self.key(id, None)).0);
+        future::join_all(loads).await.into_iter()
+            .find_map(Result::err).map_or(Ok(()), Err)
+    }
+
+    /// Returns the time taken by each currently loaded module to load, and the
+    /// imports for which it waited.
+    pub fn load_report(&self) -> LoadReport {
+        let modules = self.modules.lock().unwrap();
+        let registry = self.registry();
+        let mut timings: Vec<(Instant, ModuleTiming)> = modules.iter()
+            .filter_map(|(key, instance)| {
+                let timing = instance.timing.lock().unwrap();
+                Some((timing.started?, ModuleTiming {
+                    name: registry.module(key.module).manifest.name,
+                    started: Duration::ZERO,
+                    elapsed: timing.elapsed,
+                    failed: timing.failed,
+                    waits: timing.waits.clone(),
+                }))
+            }).collect();
+        timings.sort_by_key(|(started, _)| *started);
+        let first = timings.first().map(|(started, _)| *started);
+        LoadReport {
+            modules: timings.into_iter().map(|(started, timing)| ModuleTiming {
+                started: started - first.unwrap(), ..timing
+            }).collect(),
+        }
+    }
+}
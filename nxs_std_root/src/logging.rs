@@ -0,0 +1,520 @@
+//! The routing of the log messages of the modules of a [`StdRootModule`] to
+//! configurable sinks, and the bridging of the `log` and `tracing` facades.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use nxs_interface::{
+    logging::{Level, Logger, Record, Value},
+    root::Version,
+};
+use serde::Deserialize;
+use serde_json::{Map, json};
+
+use crate::StdRootModule;
+
+/// The size in bytes at which a log file is rotated, unless configured
+/// otherwise.
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+
+/// The number of rotated log files kept, unless configured otherwise.
+const DEFAULT_KEEP: usize = 5;
+
+/// The levels of log messages written, and where they are written.
+#[derive(Clone, Debug)]
+pub struct LogConfig {
+    /// The least severe level written for modules not listed in
+    /// `module_levels`.
+    pub level: Level,
+
+    /// The least severe level written for specific modules, by name, or for
+    /// specific targets of the `log` and `tracing` facades, by path prefix.
+    pub module_levels: HashMap<String, Level>,
+
+    /// The sinks to which every message written is sent.
+    pub sinks: Vec<LogSink>,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: Level::Info, module_levels: HashMap::new(),
+            sinks: vec![LogSink::Stderr { format: LogFormat::Text }],
+        }
+    }
+}
+
+/// A destination for log messages, configured as a table in the array
+/// `[[root.log_sinks]]`, whose `kind` is the name of the variant, e.g.
+/// ```toml
+/// [[root.log_sinks]]
+/// kind = "file"
+/// path = "nexus.log"
+/// format = "json"
+/// max_bytes = 1048576
+/// keep = 3
+/// ```
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LogSink {
+    /// The standard error stream of the process.
+    Stderr {
+        #[serde(default)]
+        format: LogFormat,
+    },
+
+    /// A file, which is renamed with the suffix `.1` when it would exceed
+    /// `max_bytes`, after renaming each older file with suffix `.n` to
+    /// `.n+1` and deleting any beyond `keep`.
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        format: LogFormat,
+        #[serde(default = "default_max_bytes")]
+        max_bytes: u64,
+        #[serde(default = "default_keep")]
+        keep: usize,
+    },
+}
+
+/// The format in which a [`LogSink`] writes messages, one per line.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// For example,
+    /// `2026-10-17T12:00:00.000Z  INFO Commands 0.1.0: joined user="alice"`.
+    #[default]
+    Text,
+
+    /// JSON objects with the keys `time`, `level`, `module`, `version`,
+    /// `target`, `message` and `fields`.
+    Json,
+}
+
+fn default_max_bytes() -> u64 { DEFAULT_MAX_BYTES }
+fn default_keep() -> usize { DEFAULT_KEEP }
+
+/// The current log levels and sinks of a [`StdRootModule`].
+pub(crate) struct LogRouter {
+    levels: RwLock<Levels>,
+    sinks: Mutex<Vec<Sink>>,
+}
+
+struct Levels {
+    default: Level,
+    modules: HashMap<String, Level>,
+}
+
+/// An open [`LogSink`].
+struct Sink {
+    config: LogSink,
+    file: Option<File>,
+    written: u64,
+}
+
+/// A log message, together with its origin.
+struct Entry<'a> {
+    module: &'a str,
+    version: Option<&'a Version>,
+    record: &'a Record<'a>,
+}
+
+impl LogRouter {
+    pub fn new(config: &LogConfig) -> Self {
+        let router = Self {
+            levels: RwLock::new(Levels {
+                default: config.level, modules: config.module_levels.clone(),
+            }),
+            sinks: Mutex::default(),
+        };
+        router.set_sinks(&config.sinks);
+        router
+    }
+
+    /// Replaces the levels with those in `config`.
+    pub fn set_levels(&self, config: &LogConfig) {
+        let mut levels = self.levels.write().unwrap();
+        levels.default = config.level;
+        levels.modules = config.module_levels.clone();
+    }
+
+    /// Replaces the sinks with `sinks`, each file of which is opened when it
+    /// is first written.
+    pub fn set_sinks(&self, sinks: &[LogSink]) {
+        *self.sinks.lock().unwrap() = sinks.iter().map(|config| Sink {
+            config: config.clone(), file: None, written: 0,
+        }).collect();
+    }
+
+    /// Returns the least severe level written for the module or target
+    /// `name`, as given for `name` itself or for the longest of its prefixes
+    /// ending before a `::`.
+    fn level(&self, name: &str) -> Level {
+        let levels = self.levels.read().unwrap();
+        let mut prefix = name;
+        loop {
+            if let Some(&level) = levels.modules.get(prefix) { return level; }
+            match prefix.rfind("::") {
+                Some(end) => prefix = &prefix[..end],
+                None => return levels.default,
+            }
+        }
+    }
+
+    fn enabled(&self, name: &str, level: Level) -> bool {
+        level <= self.level(name)
+    }
+
+    /// Writes `entry` to each sink, if its level is enabled for its module.
+    fn write(&self, entry: &Entry<'_>) {
+        if !self.enabled(entry.module, entry.record.level) { return; }
+        let time = SystemTime::now();
+        let (mut text, mut json) = (None, None);
+        for sink in self.sinks.lock().unwrap().iter_mut() {
+            let line = match sink.format() {
+                LogFormat::Text => text.get_or_insert_with(|| {
+                    format_text(entry, time)
+                }),
+                LogFormat::Json => json.get_or_insert_with(|| {
+                    format_json(entry, time)
+                }),
+            };
+            if let Err(error) = sink.write(line) {
+                eprintln!("error: failed to write log message: {}", error);
+            }
+        }
+    }
+}
+
+impl Sink {
+    fn format(&self) -> LogFormat {
+        match self.config {
+            LogSink::Stderr { format } | LogSink::File { format, .. } => format,
+        }
+    }
+
+    /// Writes `line`, followed by a newline.
+    fn write(&mut self, line: &str) -> io::Result<()> {
+        let (path, max_bytes, keep) = match &self.config {
+            LogSink::Stderr { .. } => {
+                eprintln!("{}", line);
+                return Ok(());
+            }
+            LogSink::File { path, max_bytes, keep, .. } => {
+                (path, *max_bytes, *keep)
+            }
+        };
+        let len = line.len() as u64 + 1;
+        let mut file = match self.file.take() {
+            Some(file) => file,
+            None => open(path, &mut self.written)?,
+        };
+        if self.written > 0 && self.written + len > max_bytes {
+            drop(file);
+            rotate(path, keep)?;
+            file = open(path, &mut self.written)?;
+        }
+        writeln!(file, "{}", line)?;
+        self.file = Some(file);
+        self.written += len;
+        Ok(())
+    }
+}
+
+/// Opens the log file at `path` for appending, setting `written` to its
+/// length.
+fn open(path: &Path, written: &mut u64) -> io::Result<File> {
+    let file = OpenOptions::new().create(true).append(true).open(path)
+        .and_then(|file| { *written = file.metadata()?.len(); Ok(file) });
+    file.map_err(|e| {
+        io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+    })
+}
+
+/// Renames the log file at `path` and its rotated predecessors, keeping at
+/// most `keep` of the latter.
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    let rotated = |n: usize| {
+        let mut name = path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    };
+    if keep == 0 { return fs::remove_file(path); }
+    let _ = fs::remove_file(rotated(keep));
+    for n in (1..keep).rev() {
+        if rotated(n).exists() { fs::rename(rotated(n), rotated(n + 1))?; }
+    }
+    fs::rename(path, rotated(1))
+}
+
+/// Formats `time` as an RFC 3339 timestamp in UTC, with millisecond precision.
+fn format_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let (secs, millis) = (since_epoch.as_secs(), since_epoch.subsec_millis());
+    let (days, secs) = ((secs / 86400) as i64, secs % 86400);
+
+    // The civil date of a day number, after Howard Hinnant's algorithm:
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524
+                       - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z", year, month, day,
+            secs / 3600, secs / 60 % 60, secs % 60, millis)
+}
+
+fn format_text(entry: &Entry<'_>, time: SystemTime) -> String {
+    let record = entry.record;
+    let level = record.level.as_str().to_uppercase();
+    let mut line = format!("{} {:>5} {}", format_time(time), level,
+                           entry.module);
+    if let Some(version) = entry.version {
+        write!(line, " {}", version).unwrap();
+    }
+    write!(line, ": {}", record.message).unwrap();
+    for (key, value) in record.fields {
+        write!(line, " {}={:?}", key, value).unwrap();
+    }
+    line
+}
+
+fn format_json(entry: &Entry<'_>, time: SystemTime) -> String {
+    let record = entry.record;
+    let fields: Map<String, serde_json::Value> = record.fields.iter()
+        .map(|(key, value)| (key.to_string(), match *value {
+            Value::Str(value)   => json!(value),
+            Value::Int(value)   => json!(value),
+            Value::UInt(value)  => json!(value),
+            Value::Float(value) => json!(value),
+            Value::Bool(value)  => json!(value),
+            Value::Display(value) => json!(value.to_string()),
+        })).collect();
+    json!({
+        "time": format_time(time),
+        "level": record.level.as_str(),
+        "module": entry.module,
+        "version": entry.version.map(Version::to_string),
+        "target": record.target,
+        "message": record.message.to_string(),
+        "fields": fields,
+    }).to_string()
+}
+
+/// The [`Logger`] given to a module, which tags its messages with the name
+/// and version of that module.
+#[derive(Clone)]
+pub(crate) struct ModuleLogger {
+    root: &'static StdRootModule,
+    module: &'static str,
+    version: Version,
+}
+
+impl ModuleLogger {
+    pub fn new(root: &'static StdRootModule, module: &'static str,
+               version: Version) -> Self {
+        Self { root, module, version }
+    }
+}
+
+impl Logger for ModuleLogger {
+    fn enabled(&self, level: Level) -> bool {
+        self.root.logs.enabled(self.module, level)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        let version = Some(&self.version);
+        self.root.logs.write(&Entry { module: self.module, version, record });
+    }
+}
+
+impl Logger for LogRouter {
+    fn enabled(&self, level: Level) -> bool {
+        LogRouter::enabled(self, "root", level)
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        self.write(&Entry { module: "root", version: None, record });
+    }
+}
+
+impl StdRootModule {
+    /// Returns the least severe level of log message currently written for
+    /// the module named `module`.
+    pub fn log_level(&self, module: &str) -> Level {
+        self.logs.level(module)
+    }
+
+    /// Sets the least severe level of log message written for the module
+    /// named `module`, or, if `level` is `None`, resets it to the default.
+    ///
+    /// The levels are also reset to those configured whenever the
+    /// configuration is [reloaded](Self::reload_config).
+    pub fn set_log_level(&self, module: &str, level: Option<Level>) {
+        let mut levels = self.logs.levels.write().unwrap();
+        match level {
+            Some(level) => levels.modules.insert(module.to_string(), level),
+            None => levels.modules.remove(module),
+        };
+    }
+
+    /// Sets the least severe level of log message written for the modules
+    /// with no level of their own.
+    pub fn set_default_log_level(&self, level: Level) {
+        self.logs.levels.write().unwrap().default = level;
+    }
+
+    /// Replaces the sinks to which log messages are written.
+    pub fn set_log_sinks(&self, sinks: &[LogSink]) {
+        self.logs.set_sinks(sinks);
+    }
+
+    /// Routes the messages of the global `log` and `tracing` facades, as used
+    /// by third-party crates, to the sinks of this root, with the target of
+    /// each message in place of the name of a module.
+    ///
+    /// Since each facade has only one global logger, this may be done for at
+    /// most one root. Returns `false` if either facade already has a logger,
+    /// in which case that facade is left unchanged.
+    ///
+    /// Each plugin has its own copy of any facade it uses, and so is not
+    /// affected by this.
+    pub fn bridge_log_facades(&'static self) -> bool {
+        let bridged_log = log::set_boxed_logger(Box::new(LogBridge(self)))
+            .is_ok();
+        if bridged_log { log::set_max_level(log::LevelFilter::Trace); }
+        let bridge = TracingBridge { root: self, spans: AtomicU64::new(1) };
+        let bridged_tracing = tracing::subscriber::set_global_default(bridge)
+            .is_ok();
+        bridged_log && bridged_tracing
+    }
+}
+
+/// Routes the messages of the `log` facade to a [`StdRootModule`].
+struct LogBridge(&'static StdRootModule);
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        let level = from_log_level(metadata.level());
+        self.0.logs.enabled(metadata.target(), level)
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        let record = Record {
+            level: from_log_level(record.level()),
+            target: record.target(),
+            message: *record.args(), fields: &[],
+        };
+        let module = record.target;
+        self.0.logs.write(&Entry { module, version: None, record: &record });
+    }
+
+    fn flush(&self) {}
+}
+
+fn from_log_level(level: log::Level) -> Level {
+    match level {
+        log::Level::Error => Level::Error,
+        log::Level::Warn  => Level::Warn,
+        log::Level::Info  => Level::Info,
+        log::Level::Debug => Level::Debug,
+        log::Level::Trace => Level::Trace,
+    }
+}
+
+/// Routes the events of the `tracing` facade to a [`StdRootModule`], with
+/// their fields, ignoring spans.
+struct TracingBridge {
+    root: &'static StdRootModule,
+    spans: AtomicU64,
+}
+
+impl tracing::Subscriber for TracingBridge {
+    fn register_callsite(&self, _metadata: &'static tracing::Metadata<'static>)
+    -> tracing::subscriber::Interest {
+        // The levels may change at any time:
+        tracing::subscriber::Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &tracing::Metadata<'_>) -> bool {
+        let level = from_tracing_level(*metadata.level());
+        self.root.logs.enabled(metadata.target(), level)
+    }
+
+    fn new_span(&self, _span: &tracing::span::Attributes<'_>)
+    -> tracing::span::Id {
+        tracing::span::Id::from_u64(self.spans.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &tracing::span::Id,
+              _values: &tracing::span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &tracing::span::Id,
+                           _follows: &tracing::span::Id) {}
+
+    fn event(&self, event: &tracing::Event<'_>) {
+        let mut visitor = FieldVisitor::default();
+        event.record(&mut visitor);
+        let fields: Vec<(&str, Value<'_>)> = visitor.fields.iter()
+            .map(|(key, value)| (*key, Value::Str(value))).collect();
+        let metadata = event.metadata();
+        let message = visitor.message.as_deref().unwrap_or_default();
+        let record = Record {
+            level: from_tracing_level(*metadata.level()),
+            target: metadata.target(),
+            message: format_args!("{}", message), fields: &fields,
+        };
+        let module = record.target;
+        self.root.logs.write(&Entry { module, version: None, record: &record });
+    }
+
+    fn enter(&self, _span: &tracing::span::Id) {}
+
+    fn exit(&self, _span: &tracing::span::Id) {}
+}
+
+fn from_tracing_level(level: tracing::Level) -> Level {
+    match level {
+        tracing::Level::ERROR => Level::Error,
+        tracing::Level::WARN  => Level::Warn,
+        tracing::Level::INFO  => Level::Info,
+        tracing::Level::DEBUG => Level::Debug,
+        tracing::Level::TRACE => Level::Trace,
+    }
+}
+
+/// Collects the fields of a `tracing` event as strings.
+#[derive(Default)]
+struct FieldVisitor {
+    message: Option<String>,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl tracing::field::Visit for FieldVisitor {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        match field.name() {
+            "message" => self.message = Some(value.to_string()),
+            name => self.fields.push((name, value.to_string())),
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field,
+                    value: &dyn fmt::Debug) {
+        match field.name() {
+            "message" => self.message = Some(format!("{:?}", value)),
+            name => self.fields.push((name, format!("{:?}", value))),
+        }
+    }
+}
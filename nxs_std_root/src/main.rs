@@ -1,3 +1,302 @@
+//! The Nexus host process, which loads a configured set of leaf modules into
+//! a [`StdRootModule`] and runs until it is signalled to shut down.
+
+use std::path::PathBuf;
+use std::process::exit;
+
+use futures::executor::block_on;
+use nxs_interface::health::HealthState;
+use nxs_std_root::{
+    Config, ModuleGraph, Registry, StdConfig, StdMetrics, StdRootModule,
+};
+
+const USAGE: &str = "\
+Usage: nxs_std_root [OPTIONS] [COMMAND]
+
+Commands:
+  run     Load the enabled modules and run until signalled [default];
+          on SIGUSR1, print the health of each module; unix only
+  graph   Print the graph of the enabled modules and their dependencies
+  status  Load the enabled modules, print the health of each, and exit
+
+Options:
+  -c, --config <PATH>  Read the configuration from PATH [default: nexus.toml]
+  -n, --dry-run        Resolve the enabled modules and their dependencies,
+                       print the load order, and exit without loading them
+  -f, --format <FMT>   Print the graph as `dot` (Graphviz) or `json`
+                       [default: dot]
+  -l, --load           Load the modules before printing the graph, to show
+                       the imports made and any modules that failed
+  -v, --verbose        Print more messages; may be repeated
+  -q, --quiet          Print only errors
+  -h, --help           Print this message and exit";
+
+/// The options given on the command line.
+struct Options {
+    command: Command,
+    config: PathBuf,
+    dry_run: bool,
+    format: Format,
+    load: bool,
+    verbosity: Verbosity,
+}
+
+/// What the process does.
+#[derive(Clone, Copy, PartialEq)]
+enum Command { Run, Graph, Status }
+
+/// The format in which to print a graph.
+#[derive(Clone, Copy, PartialEq)]
+enum Format { Dot, Json }
+
+/// How many messages to print, from least to most.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Verbosity { Quiet, Normal, Verbose, Debug }
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            command: Command::Run,
+            config: PathBuf::from("nexus.toml"),
+            dry_run: false,
+            format: Format::Dot,
+            load: false,
+            verbosity: Verbosity::Normal,
+        };
+        let format = |format: &str| match format {
+            "dot"  => Ok(Format::Dot),
+            "json" => Ok(Format::Json),
+            _ => Err(format!("unrecognised graph format `{}`", format)),
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "run" => options.command = Command::Run,
+                "graph" => options.command = Command::Graph,
+                "status" => options.command = Command::Status,
+                "-c" | "--config" => {
+                    options.config = args.next()
+                        .ok_or_else(|| format!("`{}` requires a path", arg))?
+                        .into();
+                }
+                "-n" | "--dry-run" => options.dry_run = true,
+                "-f" | "--format" => {
+                    options.format = format(&args.next().ok_or_else(|| {
+                        format!("`{}` requires a format", arg)
+                    })?)?;
+                }
+                "-l" | "--load" => options.load = true,
+                "-q" | "--quiet" => options.verbosity = Verbosity::Quiet,
+                "-v" | "--verbose" => {
+                    options.verbosity = match options.verbosity {
+                        Verbosity::Quiet | Verbosity::Normal => Verbosity::Verbose,
+                        _ => Verbosity::Debug,
+                    };
+                }
+                "-vv" => options.verbosity = Verbosity::Debug,
+                "-h" | "--help" => {
+                    println!("{}", USAGE);
+                    exit(0);
+                }
+                _ => if let Some(path) = arg.strip_prefix("--config=") {
+                    options.config = path.into();
+                } else if let Some(name) = arg.strip_prefix("--format=") {
+                    options.format = format(name)?;
+                } else {
+                    return Err(format!("unrecognised argument `{}`", arg));
+                },
+            }
+        }
+        Ok(options)
+    }
+}
+
 fn main() {
-    println!("Hello, world!");
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            exit(2);
+        }
+    };
+    match block_on(run(&options)) {
+        Ok(true) => (),
+        Ok(false) => exit(1),
+        Err(message) => {
+            eprintln!("error: {}", message);
+            exit(1);
+        }
+    }
+}
+
+/// Runs the host process, returning whether it completed cleanly.
+async fn run(options: &Options) -> Result<bool, String> {
+    let say = |level: Verbosity, message: &dyn std::fmt::Display| {
+        if options.verbosity >= level { eprintln!("{}", message); }
+    };
+
+    let config = Config::load(&options.config).map_err(|e| e.to_string())?;
+    say(Verbosity::Debug, &format_args!(
+        "read configuration from {}", config.path.display()));
+    let registry = build_registry(&config, options.verbosity)?;
+
+    // Resolve the modules before loading any, to report all problems at once:
+    let missing = registry.missing();
+    for (manifest, interface) in &missing {
+        eprintln!("error: module `{}` requires `{}` {}, which no enabled \
+                   module provides", manifest.name,
+                  interface.type_info.short_name(), interface.version);
+    }
+    if options.command == Command::Graph && !options.load {
+        print_graph(&registry.graph(), options.format);
+        return Ok(missing.is_empty());
+    }
+    let manifests = registry.load_order().map_err(|e| e.to_string())?;
+    if options.dry_run {
+        for manifest in manifests {
+            println!("{} {}", manifest.name, manifest.version);
+            for interface in &manifest.provides {
+                println!("  provides {} {}",
+                         interface.type_info.short_name(), interface.version);
+            }
+            for interface in &manifest.requires {
+                println!("  requires {} {}",
+                         interface.type_info.short_name(), interface.version);
+            }
+            for interface in &manifest.optional {
+                println!("  optionally requires {} {}",
+                         interface.type_info.short_name(), interface.version);
+            }
+        }
+        return Ok(missing.is_empty());
+    }
+    if !missing.is_empty() {
+        return Err("unresolved dependencies".to_string());
+    }
+    // Without signals, the host could never be shut down cleanly:
+    if options.command == Command::Run && !cfg!(unix) {
+        return Err("`run` is not supported on this platform; use `graph`, \
+                    `status` or `--dry-run`".to_string());
+    }
+    let order: Vec<&'static str>
+        = manifests.into_iter().map(|manifest| manifest.name).collect();
+
+    let root: &'static StdRootModule
+        = Box::leak(Box::new(StdRootModule::with_config(registry, config)));
+    if !root.bridge_log_facades() {
+        say(Verbosity::Verbose, &"warning: could not bridge the `log` and \
+                                  `tracing` facades");
+    }
+    let metrics = root.config().metrics();
+    if let Some(address) = metrics.address {
+        let address = root.serve_metrics(address).map_err(|e| {
+            format!("failed to serve metrics on {}: {}", address, e)
+        })?;
+        say(Verbosity::Verbose, &format_args!(
+            "serving metrics at http://{}/metrics", address));
+    }
+    if let Some(file) = metrics.file {
+        root.write_metrics_every(file, metrics.interval);
+    }
+    let loaded = root.load_modules(&order).await;
+    say(Verbosity::Verbose, &root.load_report());
+    if options.command == Command::Graph {
+        print_graph(&root.graph(), options.format);
+        let report = root.shutdown(&root.config().shutdown()).await;
+        say(Verbosity::Verbose, &report);
+        return Ok(loaded.is_ok() && report.is_clean());
+    }
+    if options.command == Command::Status {
+        let status = root.status();
+        println!("{}", status);
+        let report = root.shutdown(&root.config().shutdown()).await;
+        say(Verbosity::Verbose, &report);
+        return Ok(status.overall() != HealthState::Failed && report.is_clean());
+    }
+    loaded.map_err(|e| e.to_string())?;
+    say(Verbosity::Normal, &format_args!(
+        "loaded {} modules", root.loaded().len()));
+
+    let report = wait_and_shut_down(root).await?;
+    if report.is_clean() {
+        say(Verbosity::Normal, &report);
+    } else {
+        eprintln!("{}", report);
+    }
+    Ok(report.is_clean())
+}
+
+fn print_graph(graph: &ModuleGraph, format: Format) {
+    match format {
+        Format::Dot  => print!("{}", graph.to_dot()),
+        Format::Json => println!("{}", graph.to_json()),
+    }
+}
+
+/// Registers each module enabled by `config`, from the built-in modules or
+/// from plugins.
+fn build_registry(config: &Config, verbosity: Verbosity)
+-> Result<Registry, String> {
+    let mut registry = Registry::new();
+    registry.register::<StdConfig>();
+    registry.register::<StdMetrics>();
+    for name in &config.root.modules {
+        if register_builtin(&mut registry, name) { continue; }
+        let dir = config.plugins_dir().ok_or_else(|| format!(
+            "`{}` is not a built-in module set, and no plugins directory is \
+             configured", name))?;
+        let path = dir.join(format!("{}{}.{}", std::env::consts::DLL_PREFIX,
+                                    name, std::env::consts::DLL_EXTENSION));
+        if verbosity >= Verbosity::Verbose {
+            eprintln!("loading plugin {}", path.display());
+        }
+        // Plugins are trusted to the same extent as the configuration file:
+        unsafe { registry.load_plugin(&path) }.map_err(|e| {
+            format!("failed to load plugin {}: {}", path.display(), e)
+        })?;
+    }
+    config.apply_to(&mut registry).map_err(|e| e.to_string())?;
+    Ok(registry)
+}
+
+/// Registers the modules of the built-in module set `name`, returning whether
+/// there is such a set.
+fn register_builtin(registry: &mut Registry, name: &str) -> bool {
+    match name {
+        "nxs_std_text" => registry.register::<nxs_std_text::StdTextManager>(),
+        "nxs_std_cmds" => registry.register::<nxs_std_cmds::Commands>(),
+        "nxs_std_events" => {
+            registry.register::<nxs_std_events::StdEventBus>()
+        }
+        _ => return false,
+    };
+    true
+}
+
+#[cfg(unix)]
+async fn wait_and_shut_down(root: &'static StdRootModule)
+-> Result<nxs_std_root::ShutdownReport, String> {
+    let config = root.config().shutdown();
+    print_status_on_signal(root).map_err(|e| e.to_string())?;
+    root.shutdown_on_signal(&config).await.map_err(|e| e.to_string())
+}
+
+/// Starts a thread which prints the health of each module whenever the
+/// process receives SIGUSR1.
+#[cfg(unix)]
+fn print_status_on_signal(root: &'static StdRootModule)
+-> std::io::Result<()> {
+    use signal_hook::{consts::SIGUSR1, iterator::Signals};
+    let mut signals = Signals::new([SIGUSR1])?;
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            eprintln!("{}", root.status());
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+async fn wait_and_shut_down(_root: &'static StdRootModule)
+-> Result<nxs_std_root::ShutdownReport, String> {
+    unreachable!("`run` is refused on platforms without signals")
 }
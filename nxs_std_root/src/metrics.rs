@@ -0,0 +1,430 @@
+//! The metrics of a [`StdRootModule`] and its modules, the [`StdMetrics`]
+//! module through which leaf modules register them, and their exposition in
+//! the Prometheus text format.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write as _};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    util::dyn_cast::{DynCast, DynCastExt},
+    root::{LeafModule, RootModule},
+    metrics::{self, Counter, Gauge, Histogram, Metrics},
+};
+
+use crate::{StdRootModule, importer::Importer};
+
+/// The interval at which metrics are written to a file, unless configured
+/// otherwise.
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+
+/// The prefix of the names of the metrics of the root itself, which modules
+/// may not use.
+const ROOT_PREFIX: &str = "nxs_";
+
+/// The time allowed for a client of the metrics server to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Where the metrics of a [`StdRootModule`] are exposed.
+#[derive(Clone, Debug)]
+pub struct MetricsConfig {
+    /// The address on which to serve the metrics over HTTP, if any.
+    pub address: Option<SocketAddr>,
+
+    /// The file to which to write the metrics periodically, if any.
+    pub file: Option<PathBuf>,
+
+    /// The interval at which to write the metrics to `file`.
+    pub interval: Duration,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { address: None, file: None, interval: DEFAULT_INTERVAL }
+    }
+}
+
+/// Every metric registered with a [`StdRootModule`], by name.
+#[derive(Default)]
+pub(crate) struct MetricStore {
+    families: Mutex<BTreeMap<String, Family>>,
+}
+
+/// The metrics with the same name, distinguished by their labels.
+struct Family {
+    help: String,
+    kind: &'static str,
+
+    /// The name of the module which registered the metrics, or `None` if the
+    /// root did.
+    owner: Option<&'static str>,
+
+    /// Each metric, by its labels as rendered, e.g. `{module="Commands"}`.
+    series: BTreeMap<String, Metric>,
+}
+
+#[derive(Clone)]
+enum Metric {
+    Counter(Counter),
+    Gauge(Gauge),
+    Histogram(Histogram),
+}
+
+impl Metric {
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Counter(_)   => "counter",
+            Self::Gauge(_)     => "gauge",
+            Self::Histogram(_) => "histogram",
+        }
+    }
+}
+
+impl MetricStore {
+    /// Returns the metric named `name` with the given labels, registering it
+    /// as `new` on behalf of the module `owner`, or of the root if `owner` is
+    /// `None`, if it does not already exist.
+    ///
+    /// Fails if a metric of another kind, or of another owner, is already
+    /// registered as `name`.
+    fn register(
+        &self, name: &str, help: &str, labels: &[(&str, &str)],
+        owner: Option<&'static str>, new: impl FnOnce() -> Metric,
+    ) -> Result<Metric, String> {
+        let mut families = self.families.lock().unwrap();
+        let metric = new();
+        let family = families.entry(name.to_string()).or_insert_with(|| {
+            Family {
+                help: help.to_string(), kind: metric.kind(), owner,
+                series: BTreeMap::new(),
+            }
+        });
+        if family.owner != owner {
+            return Err(match family.owner {
+                Some(other) => format!("`{}` is already registered by module \
+                                        `{}`", name, other),
+                None => format!("`{}` is already registered by the root", name),
+            });
+        }
+        if family.kind != metric.kind() {
+            return Err(format!("`{}` is already registered as a {}",
+                               name, family.kind));
+        }
+        Ok(family.series.entry(render_labels(labels))
+            .or_insert(metric).clone())
+    }
+
+    /// Returns the counter of the root named `name` with the given labels.
+    pub fn counter(&self, name: &str, help: &str, labels: &[(&str, &str)])
+    -> Result<Counter, String> {
+        let new = || Metric::Counter(Counter::new());
+        match self.register(name, help, labels, None, new)? {
+            Metric::Counter(counter) => Ok(counter),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the gauge of the root named `name` with the given labels.
+    pub fn gauge(&self, name: &str, help: &str, labels: &[(&str, &str)])
+    -> Result<Gauge, String> {
+        let new = || Metric::Gauge(Gauge::new());
+        match self.register(name, help, labels, None, new)? {
+            Metric::Gauge(gauge) => Ok(gauge),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Records that the module `name` took `elapsed` to load, or failed to.
+    ///
+    /// As modules may not register metrics under [`ROOT_PREFIX`], those of the
+    /// root cannot fail to register; if one somehow did, it is not recorded.
+    pub fn record_load(&self, name: &str, elapsed: Duration, loaded: bool) {
+        let labels = [("module", name)];
+        if loaded {
+            let gauge = self.gauge(
+                "nxs_module_load_seconds",
+                "The time taken by each module to load, when it was last \
+                 loaded.", &labels);
+            if let Ok(gauge) = gauge { gauge.set(elapsed.as_secs_f64()); }
+        } else {
+            let counter = self.counter(
+                "nxs_module_load_failures_total",
+                "The number of times each module failed to load.", &labels);
+            if let Ok(counter) = counter { counter.inc(); }
+        }
+    }
+
+    /// Records an attempt to import `interface` from the module `provider`.
+    pub fn record_import(
+        &self, interface: TypeInfo, provider: &str, imported: bool,
+    ) {
+        let interface = interface.short_name();
+        let interface = interface.trim_start_matches("dyn ");
+        let labels = [("interface", interface), ("provider", provider)];
+        let (name, help) = match imported {
+            true => ("nxs_imports_total",
+                     "The number of imports of each interface from each \
+                      provider."),
+            false => ("nxs_import_failures_total",
+                      "The number of failed imports of each interface from \
+                       each provider."),
+        };
+        if let Ok(counter) = self.counter(name, help, &labels) {
+            counter.inc();
+        }
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    fn render(&self) -> String {
+        let mut text = String::new();
+        for (name, family) in self.families.lock().unwrap().iter() {
+            let help = family.help.replace('\\', "\\\\").replace('\n', "\\n");
+            writeln!(text, "# HELP {} {}", name, help).unwrap();
+            writeln!(text, "# TYPE {} {}", name, family.kind).unwrap();
+            for (labels, metric) in &family.series {
+                render_metric(&mut text, name, labels, metric);
+            }
+        }
+        text
+    }
+}
+
+/// Renders labels as in the Prometheus text format, e.g. `{module="X"}`, or
+/// as an empty string if there are none.
+fn render_labels(labels: &[(&str, &str)]) -> String {
+    if labels.is_empty() { return String::new(); }
+    let labels: Vec<String> = labels.iter().map(|(key, value)| {
+        let value = value.replace('\\', "\\\\").replace('"', "\\\"")
+            .replace('\n', "\\n");
+        format!("{}=\"{}\"", key, value)
+    }).collect();
+    format!("{{{}}}", labels.join(","))
+}
+
+fn render_metric(text: &mut String, name: &str, labels: &str, metric: &Metric) {
+    match metric {
+        Metric::Counter(counter) => {
+            writeln!(text, "{}{} {}", name, labels, counter.get()).unwrap();
+        }
+        Metric::Gauge(gauge) => {
+            writeln!(text, "{}{} {}", name, labels, render_value(gauge.get()))
+                .unwrap();
+        }
+        Metric::Histogram(histogram) => {
+            let snapshot = histogram.snapshot();
+            // The `le` label is added to any others, within the same braces:
+            let other = labels.trim_start_matches('{').trim_end_matches('}');
+            let sep = if other.is_empty() { "" } else { "," };
+            let buckets = snapshot.buckets.iter()
+                .map(|&(bound, count)| (render_value(bound), count))
+                .chain(Some(("+Inf".to_string(), snapshot.count)));
+            for (bound, count) in buckets {
+                writeln!(text, "{}_bucket{{{}{}le=\"{}\"}} {}",
+                         name, other, sep, bound, count).unwrap();
+            }
+            writeln!(text, "{}_sum{} {}", name, labels,
+                     render_value(snapshot.sum)).unwrap();
+            writeln!(text, "{}_count{} {}", name, labels, snapshot.count)
+                .unwrap();
+        }
+    }
+}
+
+/// Renders a value as in the Prometheus text format.
+fn render_value(value: f64) -> String {
+    match value {
+        v if v.is_nan()            => "NaN".to_string(),
+        f64::INFINITY              => "+Inf".to_string(),
+        f64::NEG_INFINITY          => "-Inf".to_string(),
+        v                          => v.to_string(),
+    }
+}
+
+/// Converts the name of a module to the prefix of the names of its metrics,
+/// e.g. `StdTextManager` to `std_text_manager`.
+fn prefix(module: &str) -> String {
+    let mut prefix = String::new();
+    let mut previous = '_';
+    for c in module.chars() {
+        if c.is_ascii_uppercase() && previous.is_ascii_alphanumeric()
+            && !previous.is_ascii_uppercase() {
+            prefix.push('_');
+        }
+        prefix.push(if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() }
+                    else { '_' });
+        previous = c;
+    }
+    prefix
+}
+
+impl StdRootModule {
+    /// Renders the metrics registered by every module, together with those
+    /// of the root itself, in the Prometheus text exposition format.
+    ///
+    /// The metrics of the root are:
+    /// * `nxs_modules_loaded`, the number of modules loaded;
+    /// * `nxs_module_load_seconds{module}`, the time taken by each module to
+    ///   load, when it was last loaded;
+    /// * `nxs_module_load_failures_total{module}`, the number of times each
+    ///   module failed to load;
+    /// * `nxs_imports_total{interface,provider}`, the number of imports of
+    ///   each interface from each provider; and
+    /// * `nxs_import_failures_total{interface,provider}`, the number of
+    ///   failed imports of each interface from each provider.
+    pub fn render_metrics(&self) -> String {
+        let help = "The number of modules loaded.";
+        if let Ok(gauge) = self.metrics.gauge("nxs_modules_loaded", help, &[]) {
+            gauge.set(self.loaded().len() as f64);
+        }
+        self.metrics.render()
+    }
+
+    /// Writes the metrics, as by [`render_metrics`](Self::render_metrics), to
+    /// the file at `path`, replacing it atomically.
+    pub fn write_metrics(&self, path: &Path) -> io::Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+        std::fs::write(&temporary, self.render_metrics())?;
+        std::fs::rename(&temporary, path)
+    }
+
+    /// Starts a thread which writes the metrics to the file at `path` as by
+    /// [`write_metrics`](Self::write_metrics), immediately and then once every
+    /// `interval`, logging any errors.
+    pub fn write_metrics_every(
+        &'static self, path: PathBuf, interval: Duration,
+    ) {
+        std::thread::spawn(move || loop {
+            if let Err(error) = self.write_metrics(&path) {
+                nxs::nxs_error!(&self.logs,
+                                "failed to write metrics to {}: {}",
+                                path.display(), error);
+            }
+            std::thread::sleep(interval);
+        });
+    }
+
+    /// Starts a thread serving the metrics, as by
+    /// [`render_metrics`](Self::render_metrics), over HTTP at the path
+    /// `/metrics` on `address`, and returns the address bound, which differs
+    /// from `address` if its port is 0.
+    pub fn serve_metrics(&'static self, address: SocketAddr)
+    -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| self.respond(stream));
+                if let Err(error) = result {
+                    nxs::nxs_debug!(&self.logs, "failed to serve metrics: {}",
+                                    error);
+                }
+            }
+        });
+        Ok(address)
+    }
+
+    /// Responds to one HTTP request for the metrics.
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut request = String::new();
+        reader.read_line(&mut request)?;
+        // The headers are read and ignored:
+        let mut line = String::new();
+        while reader.read_line(&mut line)? > 2 { line.clear(); }
+
+        let mut words = request.split_whitespace();
+        let (status, body) = match (words.next(), words.next()) {
+            (Some("GET"), Some("/metrics")) => {
+                ("200 OK", self.render_metrics())
+            }
+            (Some("GET"), _) => ("404 Not Found", "not found\n".to_string()),
+            _ => ("405 Method Not Allowed", "method not allowed\n".to_string()),
+        };
+        write!(stream, "HTTP/1.1 {}\r\n\
+                        Content-Type: text/plain; version=0.0.4\r\n\
+                        Content-Length: {}\r\n\
+                        Connection: close\r\n\r\n{}",
+               status, body.len(), body)?;
+        stream.flush()
+    }
+}
+
+/// The standard provider of [`Metrics`], which registers the metrics of the
+/// module importing it with the [`StdRootModule`] loading it.
+///
+/// A separate instance is loaded for each importing module, which prefixes
+/// the name of each metric with that of the module, converted to snake case,
+/// e.g. `std_text_manager_`. If the root itself imports it, the prefix is
+/// `root_`. A name that would start with `nxs_`, which is reserved for the
+/// metrics of the root, or that is already registered by another module whose
+/// name has the same prefix, is rejected.
+#[derive(DynCast, LeafModule)]
+#[dyn_cast(base_traits(LeafModule, Metrics))]
+#[leaf_module(provides(Metrics), scope = "per_importer")]
+pub struct StdMetrics {
+    root: &'static StdRootModule,
+
+    /// The name of the importing module, or `None` if it is the root.
+    owner: Option<&'static str>,
+    prefix: String,
+}
+
+impl StdMetrics {
+    async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+        let importer = root.cast_ref::<Importer>().ok_or_else(|| {
+            let message = "must be loaded by a `StdRootModule`";
+            nxs::Error::module("StdMetrics", message)
+        })?;
+        let owner = root.importer();
+        let prefix = prefix(owner.unwrap_or("root"));
+        Ok(StdMetrics { root: importer.root(), owner, prefix })
+    }
+
+    fn register(&self, name: &str, help: &str, new: impl FnOnce() -> Metric)
+    -> nxs::Result<Metric> {
+        if !metrics::is_valid_name(name) {
+            return Err(nxs::Error::module(
+                "StdMetrics", format!("invalid metric name `{}`", name)));
+        }
+        let name = format!("{}_{}", self.prefix, name);
+        if name.starts_with(ROOT_PREFIX) {
+            return Err(nxs::Error::module("StdMetrics", format!(
+                "metric name `{}` uses the prefix `{}`, which is reserved",
+                name, ROOT_PREFIX)));
+        }
+        self.root.metrics.register(&name, help, &[], self.owner, new)
+            .map_err(|message| nxs::Error::module("StdMetrics", message))
+    }
+}
+
+impl Metrics for StdMetrics {
+    fn counter(&self, name: &str, help: &str) -> nxs::Result<Counter> {
+        match self.register(name, help, || Metric::Counter(Counter::new()))? {
+            Metric::Counter(counter) => Ok(counter),
+            _ => unreachable!(),
+        }
+    }
+
+    fn gauge(&self, name: &str, help: &str) -> nxs::Result<Gauge> {
+        match self.register(name, help, || Metric::Gauge(Gauge::new()))? {
+            Metric::Gauge(gauge) => Ok(gauge),
+            _ => unreachable!(),
+        }
+    }
+
+    fn histogram(&self, name: &str, help: &str, buckets: &[f64])
+    -> nxs::Result<Histogram> {
+        let new = || Metric::Histogram(Histogram::new(buckets));
+        match self.register(name, help, new)? {
+            Metric::Histogram(histogram) => Ok(histogram),
+            _ => unreachable!(),
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! Loading of leaf modules from plugins into a [`Registry`].
+
+use std::error::Error as StdError;
+use std::ffi::{CStr, OsStr};
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nxs_interface::{
+    root::{LoadFn, Manifest},
+    plugin::{
+        PluginDeclaration, PluginRegistrar,
+        ABI_VERSION, ENTRY_SYMBOL, RUSTC_VERSION, INTERFACE_VERSION,
+    },
+};
+use libloading::Library;
+use semver::{Version, VersionReq};
+
+use crate::Registry;
+
+/// The reason that a plugin could not be loaded.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PluginError {
+    /// The plugins directory could not be read.
+    Io(io::Error),
+    /// The file could not be opened as a shared library, or does not export
+    /// [`ENTRY_SYMBOL`].
+    Library(libloading::Error),
+    /// The plugin declares a different version of the plugin ABI.
+    Abi { plugin: u32, root: u32 },
+    /// The plugin was built by a different version of `rustc`.
+    Rustc { plugin: String, root: &'static str },
+    /// The plugin was built against an incompatible version of
+    /// `nxs_interface`.
+    Interface { plugin: String, root: &'static str },
+}
+
+impl fmt::Display for PluginError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PluginError::Io(error) => write!(f, "{}", error),
+            PluginError::Library(error) => write!(f, "{}", error),
+            PluginError::Abi { plugin, root } => write!(f,
+                "plugin has ABI version {}, but the root requires {}",
+                plugin, root),
+            PluginError::Rustc { plugin, root } => write!(f,
+                "plugin was built by `{}`, but the root by `{}`",
+                plugin, root),
+            PluginError::Interface { plugin, root } => write!(f,
+                "plugin was built against nxs_interface {}, which is \
+                 incompatible with the root's {}", plugin, root),
+        }
+    }
+}
+
+impl StdError for PluginError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        match self {
+            PluginError::Io(error) => Some(error),
+            PluginError::Library(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// The result of loading a single plugin.
+pub type PluginResult = Result<(), PluginError>;
+
+impl From<io::Error> for PluginError {
+    fn from(error: io::Error) -> Self { PluginError::Io(error) }
+}
+
+impl From<libloading::Error> for PluginError {
+    fn from(error: libloading::Error) -> Self { PluginError::Library(error) }
+}
+
+impl Registry {
+    /// Loads each plugin in the directory `dir`, that is, each file with the
+    /// platform's extension for shared libraries, in order of file name.
+    ///
+    /// Returns the result of loading each plugin, or an error if the directory
+    /// could not be read.
+    ///
+    /// # Safety
+    ///
+    /// See [`load_plugin`](Self::load_plugin).
+    pub unsafe fn load_plugins(&mut self, dir: &Path)
+    -> Result<Vec<(PathBuf, PluginResult)>, PluginError> {
+        let extension = OsStr::new(std::env::consts::DLL_EXTENSION);
+        let mut paths = Vec::new();
+        for entry in dir.read_dir()? {
+            let path = entry?.path();
+            if path.extension() == Some(extension) && path.is_file() {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        Ok(paths.into_iter().map(|path| {
+            let result = self.load_plugin(&path);
+            (path, result)
+        }).collect())
+    }
+
+    /// Loads the plugin at `path`, registering each leaf module it declares.
+    ///
+    /// The plugin is never unloaded, as the modules it provides may be used
+    /// for the remainder of the program.
+    ///
+    /// # Safety
+    ///
+    /// Loading a plugin runs arbitrary code from it, and trusts that any
+    /// export named [`ENTRY_SYMBOL`] is a valid [`PluginDeclaration`]. Beyond
+    /// the checks made on the declaration, the plugin must have been built
+    /// against `nxs_interface` with the same features as this root, so that
+    /// the types shared between them are identical.
+    pub unsafe fn load_plugin(&mut self, path: &Path) -> PluginResult {
+        let library = Library::new(path)?;
+        let declaration = *library
+            .get::<*const PluginDeclaration>(ENTRY_SYMBOL.as_bytes())?;
+        self.add_plugin(&*declaration)?;
+        std::mem::forget(library);
+        Ok(())
+    }
+
+    /// Checks that `declaration` is compatible with this root, and if so,
+    /// registers each leaf module it declares.
+    ///
+    /// # Safety
+    ///
+    /// The string pointers of `declaration` must be valid, if its ABI version
+    /// matches [`ABI_VERSION`].
+    pub(crate) unsafe fn add_plugin(
+        &mut self, declaration: &PluginDeclaration,
+    ) -> PluginResult {
+        // Only the ABI version may be read until it is known to match:
+        if declaration.abi_version != ABI_VERSION {
+            return Err(PluginError::Abi {
+                plugin: declaration.abi_version, root: ABI_VERSION,
+            });
+        }
+        let read = |s| CStr::from_ptr(s).to_string_lossy().into_owned();
+        let rustc_version = read(declaration.rustc_version);
+        if rustc_version != RUSTC_VERSION {
+            return Err(PluginError::Rustc {
+                plugin: rustc_version, root: RUSTC_VERSION,
+            });
+        }
+        let interface_version = read(declaration.interface_version);
+        if !is_compatible(&interface_version) {
+            return Err(PluginError::Interface {
+                plugin: interface_version, root: INTERFACE_VERSION,
+            });
+        }
+        (declaration.register)(self);
+        Ok(())
+    }
+}
+
+/// Returns whether a plugin built against version `plugin` of `nxs_interface`
+/// may be used by this root, which requires each version to be compatible with
+/// the other under caret requirements.
+fn is_compatible(plugin: &str) -> bool {
+    let caret = |v: &Version| VersionReq::parse(&format!("^{}", v)).unwrap();
+    let root = Version::parse(INTERFACE_VERSION)
+        .expect("The version of `nxs_interface` must be valid semver.");
+    Version::parse(plugin).is_ok_and(|plugin| {
+        caret(&root).matches(&plugin) && caret(&plugin).matches(&root)
+    })
+}
+
+impl PluginRegistrar for Registry {
+    fn add_module(&mut self, manifest: Manifest, load: LoadFn) {
+        self.add(manifest, load);
+    }
+}
@@ -0,0 +1,330 @@
+//! The [`Registry`] of leaf modules available to a root.
+
+use std::any::TypeId;
+use std::collections::HashMap;
+
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    root::{LeafModule, LoadFn, Manifest, InterfaceInfo},
+};
+
+/// The set of leaf modules that may be loaded by a [`StdRootModule`].
+///
+/// Each module is registered together with its [`Manifest`], and becomes a
+/// provider of each interface listed in the manifest's `provides`, which is
+/// identified by the [`TypeId`] of its trait object type (e.g.
+/// `dyn TextManager`).
+///
+/// An interface may have several providers, of which the one imported is
+/// chosen as follows:
+/// 1. If a provider has been [selected](Self::select) for the interface, only
+///    that provider is used.
+/// 2. Otherwise, the providers are tried in descending order of their
+///    [priority](Self::set_priority), and then in reverse order of
+///    registration, until one is found that implements a compatible version
+///    of the interface and loads successfully.
+///
+/// A module may be [restricted](Self::restrict) to importing only the
+/// interfaces declared in its manifest, together with any others allowed
+/// explicitly, so that it cannot import, for example, an administrative
+/// interface without this being visible in its manifest or configuration.
+///
+/// A module may also be registered as several [named
+/// instances](Self::add_instance), each of which is loaded separately, and is
+/// imported only by its name unless it is selected.
+///
+/// [`StdRootModule`]: crate::StdRootModule
+#[derive(Default)]
+pub struct Registry {
+    modules: Vec<Module>,
+    selected: HashMap<TypeId, ModuleId>,
+}
+
+/// The index of a module in a [`Registry`].
+pub type ModuleId = usize;
+
+pub struct Module {
+    /// The manifest of the module, whose `name` is the name of the instance
+    /// if this is a named instance.
+    pub manifest: Manifest,
+    pub load: LoadFn,
+    pub priority: i32,
+
+    /// The module of which this is a named instance, if it is one.
+    pub base: Option<ModuleId>,
+
+    /// If the module is restricted, the names of the interfaces it may import
+    /// in addition to those declared in its manifest.
+    pub allowed: Option<Vec<String>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the leaf module `M`.
+    pub fn register<M: LeafModule>(&mut self) -> &mut Self {
+        self.add(M::manifest(), M::dyn_load)
+    }
+
+    /// Registers a leaf module with the given manifest, which is loaded by
+    /// `load`, with priority 0.
+    ///
+    /// If an interface in `manifest.provides` already has a provider of the
+    /// same priority, the new module is preferred to it.
+    pub fn add(&mut self, manifest: Manifest, load: LoadFn) -> &mut Self {
+        self.modules.push(Module {
+            manifest, load, priority: 0, base: None, allowed: None,
+        });
+        self
+    }
+
+    /// Registers a named instance of the module named `module`, which has the
+    /// same manifest except that its name is `name`, and is loaded separately
+    /// from the module itself and from any other instance.
+    ///
+    /// The instance provides the same interfaces as the module, but is
+    /// imported only by [`import_named`], or if it is [selected](Self::select)
+    /// as the provider of an interface. When loaded, it is given a root whose
+    /// [`instance`] is `name`.
+    ///
+    /// [`import_named`]: nxs::root::RootModule::dyn_import_named
+    /// [`instance`]: nxs::root::RootModule::instance
+    pub fn add_instance(&mut self, name: &str, module: &str)
+    -> nxs::Result<&mut Self> {
+        let id = self.find_or_err(module)?;
+        let base_id = self.modules[id].base.unwrap_or(id);
+        let base = &self.modules[id];
+        if self.find(name).is_some() {
+            return Err(nxs::Error::module(base.manifest.name, format!(
+                "cannot add instance `{}`, as that name is already registered",
+                name,
+            )));
+        }
+        let mut manifest = base.manifest.clone();
+        // Instance names are few and registered for the life of the program:
+        manifest.name = Box::leak(name.to_string().into_boxed_str());
+        let (load, allowed) = (base.load, base.allowed.clone());
+        self.modules.push(Module {
+            manifest, load, priority: 0, base: Some(base_id), allowed,
+        });
+        Ok(self)
+    }
+
+    /// Sets the priority of the module named `name`, so that it is preferred
+    /// to any provider of the same interfaces with a lower priority.
+    pub fn set_priority(&mut self, name: &str, priority: i32)
+    -> nxs::Result<&mut Self> {
+        let id = self.find_or_err(name)?;
+        self.modules[id].priority = priority;
+        Ok(self)
+    }
+
+    /// Selects the module named `module` as the only provider of the interface
+    /// named `interface`, which is its full type name (e.g.
+    /// `dyn nxs_interface::text::TextManager`) or its short name (e.g.
+    /// `TextManager`).
+    pub fn select(&mut self, interface: &str, module: &str)
+    -> nxs::Result<&mut Self> {
+        let id = self.find_or_err(module)?;
+        let manifest = &self.modules[id].manifest;
+        let provided = manifest.provides.iter().find(|provided| {
+            is_named(&provided.type_info, interface)
+        }).ok_or_else(|| nxs::Error::module(
+            manifest.name, format!("does not provide interface `{}`", interface),
+        ))?;
+        self.selected.insert(provided.type_info.id, id);
+        Ok(self)
+    }
+
+    /// Restricts the module named `name` to importing only the interfaces
+    /// declared in its manifest, as required or optional, and those named in
+    /// `allowed`, replacing any previous restriction. Restricting a module
+    /// also restricts each of its named instances in the same way.
+    ///
+    /// Each name in `allowed` is the full or short name of an interface, as
+    /// for [`select`](Self::select). Any other import by the module fails with
+    /// [`nxs::Error::AccessDenied`].
+    pub fn restrict(&mut self, name: &str, allowed: &[&str])
+    -> nxs::Result<&mut Self> {
+        let id = self.find_or_err(name)?;
+        let allowed: Vec<String>
+            = allowed.iter().map(|name| name.to_string()).collect();
+        for module in &mut self.modules {
+            if module.base == Some(id) {
+                module.allowed = Some(allowed.clone());
+            }
+        }
+        self.modules[id].allowed = Some(allowed);
+        Ok(self)
+    }
+
+    /// Returns the manifests of all registered providers of the interface `M`,
+    /// including named instances and regardless of version, in order of
+    /// preference.
+    pub fn providers<M: ?Sized + 'static>(&self) -> Vec<&Manifest> {
+        self.preferred(TypeId::of::<M>()).into_iter()
+            .map(|id| &self.modules[id].manifest).collect()
+    }
+
+    /// Returns the manifests of all registered modules.
+    pub fn manifests(&self) -> impl Iterator<Item = &Manifest> {
+        self.modules.iter().map(|module| &module.manifest)
+    }
+
+    /// Returns each interface required by a registered module for which no
+    /// provider is registered, together with the manifest requiring it.
+    ///
+    /// Optional interfaces are not included, since a module must be able to
+    /// load without them.
+    pub fn missing(&self) -> Vec<(&Manifest, &InterfaceInfo)> {
+        self.manifests().flat_map(|manifest| {
+            manifest.requires.iter()
+                .filter(|i| self.provider(i.type_info.id).is_none())
+                .map(move |interface| (manifest, interface))
+        }).collect()
+    }
+
+    /// Returns the manifests of all registered modules, ordered so that each
+    /// module follows the providers of all the interfaces it requires,
+    /// including those it requires optionally.
+    ///
+    /// Fails with [`nxs::Error::Cycle`] if no such order exists. Interfaces
+    /// that have no provider are ignored.
+    pub fn load_order(&self) -> nxs::Result<Vec<&Manifest>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark { None, Visiting, Done }
+
+        fn visit<'a>(
+            reg: &'a Registry, id: ModuleId, marks: &mut Vec<Mark>,
+            path: &mut Vec<TypeInfo>, order: &mut Vec<&'a Manifest>,
+        ) -> nxs::Result<()> {
+            match marks[id] {
+                Mark::Done     => return Ok(()),
+                Mark::Visiting => {
+                    // The last interface in `path` leads back to `id`, which
+                    // was entered either after an earlier interface provided
+                    // by `id`, or at the start of `path`:
+                    let (last, init) = path.split_last().unwrap();
+                    let start = init.iter()
+                        .position(|i| reg.provider(i.id) == Some(id))
+                        .map_or(0, |i| i + 1);
+                    let path = Some(*last).into_iter()
+                        .chain(path[start..].iter().copied()).collect();
+                    return Err(nxs::Error::Cycle { path });
+                }
+                Mark::None => (),
+            }
+            marks[id] = Mark::Visiting;
+            let manifest = &reg.modules[id].manifest;
+            for interface in manifest.requires.iter().chain(&manifest.optional) {
+                let interface = interface.type_info;
+                if let Some(dep) = reg.provider(interface.id) {
+                    path.push(interface);
+                    visit(reg, dep, marks, path, order)?;
+                    path.pop();
+                }
+            }
+            marks[id] = Mark::Done;
+            order.push(&reg.modules[id].manifest);
+            Ok(())
+        }
+
+        let mut marks = vec![Mark::None; self.modules.len()];
+        let mut order = Vec::with_capacity(self.modules.len());
+        for id in 0..self.modules.len() {
+            visit(self, id, &mut marks, &mut Vec::new(), &mut order)?;
+        }
+        Ok(order)
+    }
+
+    /// Replaces the module `id` with one having the given manifest, which is
+    /// loaded by `load`, as if it had been registered in its place.
+    pub(crate) fn replace(&mut self, id: ModuleId, manifest: Manifest, load: LoadFn) {
+        let module = &mut self.modules[id];
+        let name = module.manifest.name;
+        module.manifest = manifest;
+        module.load = load;
+        if module.base.is_some() { module.manifest.name = name; }
+        let provides = &module.manifest.provides;
+        self.selected.retain(|&as_type, &mut selected| {
+            selected != id || provides.iter().any(|i| i.type_info.id == as_type)
+        });
+    }
+
+    /// Returns the first registered module or instance named `name`, if any.
+    pub(crate) fn find(&self, name: &str) -> Option<ModuleId> {
+        self.modules.iter().position(|module| module.manifest.name == name)
+    }
+
+    fn find_or_err(&self, name: &str) -> nxs::Result<ModuleId> {
+        self.find(name).ok_or_else(|| {
+            nxs::Error::UnknownModule { name: name.to_string() }
+        })
+    }
+
+    /// Returns the most preferred provider of the interface `as_type`, if any.
+    pub(crate) fn provider(&self, as_type: TypeId) -> Option<ModuleId> {
+        self.candidates(as_type).first().copied()
+    }
+
+    /// Returns the providers of the interface `as_type` which may be imported
+    /// without a name, in the order in which they should be tried.
+    pub(crate) fn candidates(&self, as_type: TypeId) -> Vec<ModuleId> {
+        match self.selected.get(&as_type) {
+            Some(&id) => vec![id],
+            None      => self.preferred(as_type).into_iter()
+                .filter(|&id| self.modules[id].base.is_none()).collect(),
+        }
+    }
+
+    /// Tells whether the module `id` may import the interface `as_type`.
+    pub(crate) fn may_import(&self, id: ModuleId, as_type: &TypeInfo) -> bool {
+        let module = &self.modules[id];
+        let allowed = match &module.allowed {
+            Some(allowed) => allowed,
+            None => return true,
+        };
+        let manifest = &module.manifest;
+        manifest.requires.iter().chain(&manifest.optional)
+            .any(|interface| interface.type_info == *as_type)
+            || allowed.iter().any(|name| is_named(as_type, name))
+    }
+
+    /// Returns the named instance `name`, if it provides the interface
+    /// `as_type`.
+    pub(crate) fn instance(&self, as_type: TypeId, name: &str)
+    -> Option<ModuleId> {
+        self.find(name).filter(|&id| {
+            let module = &self.modules[id];
+            module.base.is_some() && module.manifest.provides.iter()
+                .any(|interface| interface.type_info.id == as_type)
+        })
+    }
+
+    /// Returns all providers of the interface `as_type`, including named
+    /// instances, in order of preference, regardless of any selection.
+    pub(crate) fn preferred(&self, as_type: TypeId) -> Vec<ModuleId> {
+        let mut ids: Vec<ModuleId> = (0..self.modules.len()).filter(|&id| {
+            self.modules[id].manifest.provides.iter()
+                .any(|interface| interface.type_info.id == as_type)
+        }).collect();
+        ids.sort_by_key(|&id| (std::cmp::Reverse(self.modules[id].priority),
+                               std::cmp::Reverse(id)));
+        ids
+    }
+
+    pub(crate) fn module(&self, id: ModuleId) -> &Module {
+        &self.modules[id]
+    }
+}
+
+/// Tells whether `name` is the full name of the interface `interface` (e.g.
+/// `dyn nxs_interface::text::TextManager`), or its short name with or without
+/// `dyn` (e.g. `TextManager`).
+fn is_named(interface: &TypeInfo, name: &str) -> bool {
+    let short = interface.short_name();
+    interface.name == name || short == name
+        || short.trim_start_matches("dyn ") == name
+}
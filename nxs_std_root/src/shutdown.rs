@@ -0,0 +1,206 @@
+//! Graceful shutdown of the modules loaded by a [`StdRootModule`].
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+
+use nxs_interface as nxs;
+use futures::future::{self, Either, FutureExt};
+use futures_timer::Delay;
+
+use crate::{
+    Instance, InstanceKey, StdRootModule, importers_first, registry::ModuleId,
+};
+
+/// The time allowed for each module to shut down, unless configured otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The time allowed for modules to shut down.
+#[derive(Clone, Debug)]
+pub struct ShutdownConfig {
+    /// The time allowed for each module not listed in `module_timeouts`.
+    pub timeout: Duration,
+
+    /// The time allowed for each module, by name.
+    pub module_timeouts: HashMap<String, Duration>,
+}
+
+impl Default for ShutdownConfig {
+    fn default() -> Self {
+        Self { timeout: DEFAULT_TIMEOUT, module_timeouts: HashMap::new() }
+    }
+}
+
+impl ShutdownConfig {
+    /// Returns the time allowed for the module named `name` to shut down.
+    pub fn timeout_for(&self, name: &str) -> Duration {
+        self.module_timeouts.get(name).copied().unwrap_or(self.timeout)
+    }
+}
+
+/// The outcome of [`StdRootModule::shutdown`].
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownReport {
+    /// The names of the modules that stopped cleanly, in the order stopped.
+    pub stopped: Vec<&'static str>,
+
+    /// The names of the modules that failed to stop cleanly, and why.
+    pub failed: Vec<(&'static str, ShutdownFailure)>,
+}
+
+/// The reason that a module failed to stop cleanly.
+#[derive(Clone, Debug)]
+pub enum ShutdownFailure {
+    /// The module's shutdown method returned an error, or panicked.
+    Error(nxs::Error),
+
+    /// The module's shutdown method did not finish within the given time.
+    TimedOut(Duration),
+}
+
+impl ShutdownReport {
+    /// Tells whether every module stopped cleanly.
+    pub fn is_clean(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} of {} modules stopped cleanly", self.stopped.len(),
+               self.stopped.len() + self.failed.len())?;
+        for (name, failure) in &self.failed {
+            write!(f, "\nmodule `{}` {}", name, failure)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ShutdownFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error(error) => {
+                write!(f, "failed to stop: {}", error)
+            }
+            Self::TimedOut(timeout) => {
+                write!(f, "did not stop within {:?}", timeout)
+            }
+        }
+    }
+}
+
+impl StdRootModule {
+    /// Shuts down every loaded module, in reverse dependency order, so that
+    /// each module is shut down before any module that it imported from.
+    ///
+    /// Each module is given the time configured in `config` to shut down,
+    /// after which it is abandoned. Whether or not it stopped cleanly, it is
+    /// then unloaded, invalidating all handles to it.
+    pub async fn shutdown(&'static self, config: &ShutdownConfig)
+    -> ShutdownReport {
+        let mut report = ShutdownReport::default();
+        for key in self.shutdown_order() {
+            let instance = match self.modules.lock().unwrap().remove(&key) {
+                Some(instance) => instance,
+                None => continue,
+            };
+            match self.stop(key.module, instance, config).await {
+                Some((name, Ok(()))) => report.stopped.push(name),
+                Some((name, Err(failure))) => report.failed.push((name, failure)),
+                None => (),
+            }
+        }
+        self.imports.lock().unwrap().clear();
+        report
+    }
+
+    /// Shuts down `instance` of the module `id`, if it has loaded, allowing
+    /// it the time configured in `config`, and then unloads it, whether or not
+    /// it stopped cleanly.
+    ///
+    /// Returns the name of the module and whether it stopped cleanly, or
+    /// `None` if it had not loaded.
+    pub(crate) async fn stop(
+        &self, id: ModuleId, instance: Instance, config: &ShutdownConfig,
+    ) -> Option<(&'static str, Result<(), ShutdownFailure>)> {
+        let module = match instance.load.peek() {
+            Some(Ok(module)) => *module,
+            _ => {
+                instance.unload();
+                return None;
+            }
+        };
+        let name = self.registry().module(id).manifest.name;
+        let timeout = config.timeout_for(name);
+        let stopping = AssertUnwindSafe(module.shutdown()).catch_unwind()
+            .map(|result| result.unwrap_or_else(|panic| {
+                Err(nxs::Error::panicked(panic))
+            }));
+        let result = future::select(stopping.boxed(), Delay::new(timeout));
+        let outcome = match result.await {
+            Either::Left((Ok(()), _)) => Ok(()),
+            Either::Left((Err(error), _)) => Err(ShutdownFailure::Error(error)),
+            Either::Right(_) => Err(ShutdownFailure::TimedOut(timeout)),
+        };
+        instance.unload();
+        Some((name, outcome))
+    }
+
+    /// Waits until the process receives SIGINT or SIGTERM, and then shuts
+    /// down every loaded module as by [`shutdown`](Self::shutdown).
+    ///
+    /// If a second signal is received while shutting down, the process is
+    /// terminated immediately.
+    #[cfg(unix)]
+    pub async fn shutdown_on_signal(&'static self, config: &ShutdownConfig)
+    -> std::io::Result<ShutdownReport> {
+        signal::wait()?.await;
+        Ok(self.shutdown(config).await)
+    }
+
+    /// Returns the instances of the loaded modules, with the instances of each
+    /// module preceding those of the modules it imported from.
+    fn shutdown_order(&self) -> Vec<InstanceKey> {
+        let modules = self.modules.lock().unwrap();
+        let imports = self.imports.lock().unwrap();
+        let mut ids: Vec<_> = modules.keys().map(|key| key.module).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let (mut visited, mut order) = (HashSet::new(), Vec::new());
+        for id in ids.into_iter().rev() {
+            importers_first(&imports, id, &mut visited, &mut order);
+        }
+        let rank: HashMap<ModuleId, usize>
+            = order.into_iter().enumerate().map(|(i, id)| (id, i)).collect();
+        let mut keys: Vec<InstanceKey> = modules.keys().copied().collect();
+        keys.sort_by_key(|key| rank[&key.module]);
+        keys
+    }
+}
+
+#[cfg(unix)]
+mod signal {
+    use std::{io, thread};
+
+    use futures::{channel::oneshot, future::{BoxFuture, FutureExt}};
+    use signal_hook::{consts::{SIGINT, SIGTERM}, iterator::Signals, low_level};
+
+    /// Starts listening for SIGINT and SIGTERM, returning a future which
+    /// completes when the first is received. Any later signal is handled as
+    /// if no handler had been installed.
+    pub fn wait() -> io::Result<BoxFuture<'static, ()>> {
+        let mut signals = Signals::new([SIGINT, SIGTERM])?;
+        let (sender, receiver) = oneshot::channel();
+        thread::spawn(move || {
+            let mut sender = Some(sender);
+            for signal in signals.forever() {
+                match sender.take() {
+                    Some(sender) => { let _ = sender.send(()); }
+                    None => { let _ = low_level::emulate_default_handler(signal); }
+                }
+            }
+        });
+        Ok(receiver.map(|_| ()).boxed())
+    }
+}
@@ -0,0 +1,117 @@
+//! The background tasks of the modules loaded by a [`StdRootModule`], which
+//! are cancelled when those modules are unloaded.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use nxs_interface::{
+    self as nxs,
+    task::{Spawner, TaskHandle},
+};
+use futures::{executor::block_on, future::BoxFuture};
+
+use crate::{StdRootModule, logging::ModuleLogger};
+
+/// A means of running tasks to completion, such as the `spawn` function of an
+/// asynchronous runtime.
+pub(crate) type Executor = dyn Fn(BoxFuture<'static, ()>) + Send + Sync;
+
+/// The tasks spawned by one instance of a module.
+pub(crate) struct TaskSet {
+    /// The handles of the tasks which may not have finished, or `None` if
+    /// they have been cancelled, so that no more may be spawned.
+    handles: Mutex<Option<Vec<TaskHandle>>>,
+}
+
+impl Default for TaskSet {
+    fn default() -> Self {
+        Self { handles: Mutex::new(Some(Vec::new())) }
+    }
+}
+
+impl TaskSet {
+    /// Adds `handle` to the set, returning false if it has been cancelled.
+    fn insert(&self, handle: &TaskHandle) -> bool {
+        match &mut *self.handles.lock().unwrap() {
+            Some(handles) => {
+                handles.retain(|handle| !handle.is_finished());
+                handles.push(handle.clone());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Cancels every task in the set, and any added later.
+    pub fn cancel(&self) {
+        for handle in self.handles.lock().unwrap().take().unwrap_or_default() {
+            handle.cancel();
+        }
+    }
+}
+
+/// The [`Spawner`] given to an instance of a module, which reports its tasks'
+/// panics through its logger, and adds the tasks to its [`TaskSet`].
+pub(crate) struct ModuleSpawner {
+    root: &'static StdRootModule,
+    module: &'static str,
+    logger: ModuleLogger,
+    tasks: Arc<TaskSet>,
+}
+
+impl ModuleSpawner {
+    pub fn new(
+        root: &'static StdRootModule, module: &'static str,
+        logger: ModuleLogger, tasks: Arc<TaskSet>,
+    ) -> Self {
+        Self { root, module, logger, tasks }
+    }
+}
+
+impl Spawner for ModuleSpawner {
+    fn dyn_spawn(&self, name: &str, task: BoxFuture<'static, ()>)
+    -> nxs::Result<TaskHandle> {
+        let panics = self.root.metrics.counter(
+            "nxs_task_panics_total",
+            "The number of background tasks of each module that panicked.",
+            &[("module", self.module)],
+        ).ok();
+        let (name, logger) = (name.to_string(), self.logger.clone());
+        let (run, handle) = TaskHandle::new(task, move |outcome| {
+            if let Err(error @ nxs::Error::Panicked { .. }) = outcome {
+                if let Some(panics) = panics { panics.inc(); }
+                nxs::nxs_error!(&logger, { task: &name },
+                                "task `{}` failed: {}", name, error);
+            }
+        });
+        if !self.tasks.insert(&handle) {
+            return Err(nxs::Error::Cancelled);
+        }
+        self.root.spawn(run);
+        Ok(handle)
+    }
+}
+
+impl StdRootModule {
+    /// Sets the executor with which the background tasks of leaf modules are
+    /// run, in place of the default, which runs each task on its own thread.
+    ///
+    /// Tasks already spawned continue to run on the previous executor.
+    pub fn set_executor(
+        &self,
+        executor: impl Fn(BoxFuture<'static, ()>) + Send + Sync + 'static,
+    ) {
+        *self.executor.write().unwrap() = Arc::new(executor);
+    }
+
+    /// Runs `task` with the current executor.
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        let executor = Arc::clone(&self.executor.read().unwrap());
+        executor(task)
+    }
+}
+
+/// The default [`Executor`], which runs each task on its own thread.
+pub(crate) fn spawn_thread(task: BoxFuture<'static, ()>) {
+    thread::spawn(move || block_on(task));
+}
@@ -0,0 +1,2027 @@
+#![cfg(test)]
+
+use std::any::TypeId;
+use std::sync::{Mutex, atomic::{AtomicUsize, Ordering}};
+use std::{thread, time::Duration};
+
+use futures::executor::block_on;
+use nxs_interface::{
+    self as nxs, TypeInfo,
+    util::dyn_cast::DynCast,
+    root::{LeafModule, RootModule, Interface, InterfaceInfo, Version},
+};
+
+use crate::{
+    Registry, StdRootModule, PluginError, ShutdownConfig, ShutdownFailure,
+    Config, StdConfig,
+};
+
+/// Declares an interface trait with version 1.0.0.
+macro_rules! interface {
+    ($name:ident) => {
+        trait $name: LeafModule {}
+        impl Interface for dyn $name {
+            const VERSION: &'static str = "1.0.0";
+        }
+    };
+}
+
+/// Declares a leaf module providing only the interface `$interface`, which
+/// imports each of the interfaces `$requires` while loading, and nothing else.
+macro_rules! provider {
+    ($module:ident: $interface:ident) => {
+        provider!(@declare $module: $interface, provides($interface));
+        impl $module {
+            async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+                Ok($module)
+            }
+        }
+    };
+    ($module:ident: $interface:ident, requires($($requires:ident),+)) => {
+        provider!(@declare $module: $interface,
+                  provides($interface), requires($($requires),+));
+        impl $module {
+            async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+                $(root.import::<dyn $requires>().await?;)+
+                Ok($module)
+            }
+        }
+    };
+    (@declare $module:ident: $interface:ident, $($args:tt)*) => {
+        #[derive(DynCast, LeafModule)]
+        #[dyn_cast(base_traits(LeafModule, $interface))]
+        #[leaf_module($($args)*)]
+        struct $module;
+        impl $interface for $module {}
+    };
+}
+
+fn leak_root(registry: Registry) -> &'static dyn RootModule {
+    leak_std_root(registry)
+}
+
+fn leak_std_root(registry: Registry) -> &'static StdRootModule {
+    Box::leak(Box::new(StdRootModule::new(registry)))
+}
+
+#[test]
+fn import_loads_once() {
+    //! Importing the same interface repeatedly should load its provider only
+    //! once, and yield the same instance each time.
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    interface!(Counter);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Counter))]
+    #[leaf_module(provides(Counter))]
+    struct StdCounter;
+    impl Counter for StdCounter {}
+    impl StdCounter {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            LOADS.fetch_add(1, Ordering::SeqCst);
+            Ok(StdCounter)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdCounter>();
+    let root = leak_root(registry);
+    let a = block_on(root.import::<dyn Counter>()).unwrap().get().unwrap();
+    let b = block_on(root.import::<dyn Counter>()).unwrap().get().unwrap();
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    assert!(std::ptr::eq(
+        a as *const dyn Counter as *const u8,
+        b as *const dyn Counter as *const u8,
+    ));
+}
+
+#[test]
+fn import_without_provider() {
+    //! Importing an interface with no registered provider should fail.
+
+    interface!(Missing);
+
+    let root = leak_root(Registry::new());
+    match block_on(root.import::<dyn Missing>()) {
+        Err(nxs::Error::NotProvided { interface }) => {
+            assert_eq!(interface, TypeInfo::of::<dyn Missing>());
+            assert_eq!(interface.short_name(), "dyn Missing");
+        }
+        _ => panic!("expected `Error::NotProvided`"),
+    }
+}
+
+#[test]
+fn import_concurrently_loads_once() {
+    //! Importing the same interface from many threads at once should load its
+    //! provider only once, and yield the same instance to every importer.
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    interface!(Slow);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Slow))]
+    #[leaf_module(provides(Slow))]
+    struct StdSlow;
+    impl Slow for StdSlow {}
+    impl StdSlow {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            LOADS.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            Ok(StdSlow)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdSlow>();
+    let root = leak_root(registry);
+
+    let addrs: Vec<usize> = (0..8).map(|_| thread::spawn(move || {
+        let slow = block_on(root.import::<dyn Slow>()).unwrap().get().unwrap();
+        slow as *const dyn Slow as *const u8 as usize
+    })).collect::<Vec<_>>().into_iter().map(|t| t.join().unwrap()).collect();
+
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    assert!(addrs.iter().all(|&addr| addr == addrs[0]));
+}
+
+#[test]
+fn import_concurrently_fails_once() {
+    //! If a provider fails to load, every concurrent importer should receive
+    //! the same error, and loading should not be reattempted.
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    interface!(Broken);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Broken))]
+    #[leaf_module(provides(Broken))]
+    struct StdBroken;
+    impl Broken for StdBroken {}
+    impl StdBroken {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            LOADS.fetch_add(1, Ordering::SeqCst);
+            thread::sleep(Duration::from_millis(50));
+            Err(nxs::Error::module("StdBroken", "broken"))
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdBroken>();
+    let root = leak_root(registry);
+
+    let errors: Vec<String> = (0..8).map(|_| thread::spawn(move || {
+        block_on(root.import::<dyn Broken>()).err().unwrap().to_string()
+    })).collect::<Vec<_>>().into_iter().map(|t| t.join().unwrap()).collect();
+
+    let error = "module `StdBroken` failed to load: module `StdBroken`: broken";
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    assert!(errors.iter().all(|e| e == error));
+    assert_eq!(
+        block_on(root.import::<dyn Broken>()).err().unwrap().to_string(),
+        error,
+    );
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn import_cycle() {
+    //! If the provider of one interface imports another whose provider in
+    //! turn imports the first, the imports should fail with an error naming
+    //! the cycle, rather than deadlocking.
+
+    interface!(Ping);
+    interface!(Pong);
+
+    provider!(StdPing: Ping, requires(Pong));
+    provider!(StdPong: Pong, requires(Ping));
+
+    let mut registry = Registry::new();
+    registry.register::<StdPing>();
+    registry.register::<StdPong>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+
+    for _ in 0..2 {
+        let error = block_on(root.import::<dyn Ping>()).err().unwrap();
+        match error.root_cause() {
+            nxs::Error::Cycle { path } => assert_eq!(path, &[
+                TypeInfo::of::<dyn Pong>(),
+                TypeInfo::of::<dyn Ping>(),
+                TypeInfo::of::<dyn Pong>(),
+            ]),
+            _ => panic!("expected `Error::Cycle`"),
+        }
+        assert_eq!(
+            error.to_string(),
+            "module `StdPing` failed to load: module `StdPong` failed to load: \
+             dependency cycle detected: Pong -> Ping -> Pong",
+        );
+    }
+
+    match std_root.registry().load_order() {
+        Err(nxs::Error::Cycle { path }) => assert_eq!(path, &[
+            TypeInfo::of::<dyn Ping>(),
+            TypeInfo::of::<dyn Pong>(),
+            TypeInfo::of::<dyn Ping>(),
+        ]),
+        _ => panic!("expected `Error::Cycle`"),
+    }
+}
+
+#[test]
+fn import_self_cycle() {
+    //! A provider importing its own interface while loading should fail.
+
+    interface!(Narcissus);
+
+    provider!(StdNarcissus: Narcissus, requires(Narcissus));
+
+    let mut registry = Registry::new();
+    registry.register::<StdNarcissus>();
+    let root = leak_root(registry);
+
+    let error = block_on(root.import::<dyn Narcissus>()).err().unwrap();
+    assert_eq!(
+        error.root_cause().to_string(),
+        "dependency cycle detected: Narcissus -> Narcissus",
+    );
+}
+
+#[test]
+fn import_per_importer_waits() {
+    //! Instances of a per-importer module loaded for different importers are
+    //! distinct, so one waiting on a module which waits on the other is not a
+    //! cycle.
+
+    interface!(Hub);
+    interface!(Spoke);
+
+    provider!(StdHub: Hub, requires(Spoke));
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Spoke))]
+    #[leaf_module(provides(Spoke), requires(Hub), scope = "per_importer")]
+    struct StdSpoke;
+    impl Spoke for StdSpoke {}
+    impl StdSpoke {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            if root.importer().is_none() {
+                root.import::<dyn Hub>().await?;
+            }
+            Ok(StdSpoke)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdHub>().register::<StdSpoke>();
+    let root = leak_root(registry);
+
+    assert!(block_on(root.import::<dyn Spoke>()).is_ok());
+}
+
+#[test]
+fn import_not_castable() {
+    //! If a provider cannot be cast to its interface, importing it should fail
+    //! with an error listing the types to which it can be cast.
+
+    interface!(Wanted);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule), auto_traits())]
+    #[leaf_module(provides(Wanted))]
+    struct StdUnwanted;
+    impl StdUnwanted {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdUnwanted)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdUnwanted>();
+    let root = leak_root(registry);
+
+    let error = block_on(root.import::<dyn Wanted>()).err().unwrap();
+    match &error {
+        nxs::Error::NotCastable { interface, castable } => {
+            assert_eq!(*interface, TypeInfo::of::<dyn Wanted>());
+            assert!(castable.contains(&TypeInfo::of::<dyn LeafModule>()));
+        }
+        _ => panic!("expected `Error::NotCastable`"),
+    }
+    let message = error.to_string();
+    assert!(message.starts_with("provider cannot be cast to `dyn Wanted`"));
+    assert!(message.contains("StdUnwanted"));
+    assert!(message.contains("dyn LeafModule"));
+}
+
+#[test]
+fn manifest() {
+    //! The derived manifest of a leaf module should reflect its `leaf_module`
+    //! attribute, and the registry should report the interfaces it requires
+    //! that have no provider, and a load order of the modules it contains.
+
+    interface!(Named);
+    interface!(Other);
+    interface!(Absent);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Named, Other))]
+    #[leaf_module(name = "named", version = "1.2.3-beta.4")]
+    #[leaf_module(provides(Named, Other), requires(Absent))]
+    struct StdNamed;
+    impl Named for StdNamed {}
+    impl Other for StdNamed {}
+    impl StdNamed {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdNamed)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule))]
+    #[leaf_module(requires(Other, Named))]
+    struct StdUser;
+    impl StdUser {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Other>().await?;
+            Ok(StdUser)
+        }
+    }
+
+    let manifest = StdNamed::manifest();
+    assert_eq!(manifest.name, "named");
+    assert_eq!(manifest.version.to_string(), "1.2.3-beta.4");
+    assert_eq!(manifest.provides, &[
+        InterfaceInfo::of::<dyn Named>(), InterfaceInfo::of::<dyn Other>(),
+    ]);
+    assert_eq!(manifest.requires, &[InterfaceInfo::of::<dyn Absent>()]);
+
+    let manifest = StdUser::manifest();
+    assert_eq!(manifest.name, "StdUser");
+    assert_eq!(manifest.version.to_string(), env!("CARGO_PKG_VERSION"));
+    assert!(manifest.provides.is_empty());
+
+    let mut registry = Registry::new();
+    registry.register::<StdUser>().register::<StdNamed>();
+    let missing: Vec<_> = registry.missing().into_iter()
+        .map(|(manifest, interface)| (manifest.name, interface.type_info))
+        .collect();
+    assert_eq!(missing, &[("named", TypeInfo::of::<dyn Absent>())]);
+    let order: Vec<_> = registry.load_order().unwrap().into_iter()
+        .map(|manifest| manifest.name).collect();
+    assert_eq!(order, &["named", "StdUser"]);
+
+    // Both interfaces of `StdNamed` should be provided by the same instance:
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    let named = block_on(root.import::<dyn Named>()).unwrap().get().unwrap();
+    let other = block_on(root.import::<dyn Other>()).unwrap().get().unwrap();
+    assert!(std::ptr::eq(
+        named as *const dyn Named as *const u8,
+        other as *const dyn Other as *const u8,
+    ));
+    assert_eq!(std_root.loaded().len(), 1);
+}
+
+#[test]
+fn import_version() {
+    //! Importing an interface should succeed only if its provider implements
+    //! a version compatible with that expected by the importer, and importing
+    //! all of its providers should fail if none of them does.
+
+    interface!(Versioned);
+
+    provider!(StdVersioned: Versioned);
+
+    // Simulate providers compiled against other versions of `Versioned`:
+    let root_with = |version: &str| {
+        let mut manifest = StdVersioned::manifest();
+        manifest.provides[0].version = Version::parse(version).unwrap();
+        let mut registry = Registry::new();
+        registry.add(manifest, StdVersioned::dyn_load);
+        leak_root(registry)
+    };
+    let with_version = |version: &str| {
+        block_on(root_with(version).import::<dyn Versioned>()).map(|_| ())
+    };
+
+    assert!(with_version("1.0.0").is_ok());
+    assert!(with_version("1.4.2").is_ok());
+    for version in &["0.9.0", "1.0.0-alpha.1", "2.0.0"] {
+        match with_version(version) {
+            Err(nxs::Error::IncompatibleVersion {
+                interface, module, required, provided,
+            }) => {
+                assert_eq!(interface, TypeInfo::of::<dyn Versioned>());
+                assert_eq!(module, "StdVersioned");
+                assert_eq!(required.to_string(), "1.0.0");
+                assert_eq!(provided.to_string(), *version);
+            }
+            _ => panic!("expected `Error::IncompatibleVersion`"),
+        }
+    }
+    let all = block_on(root_with("2.0.0").import_all::<dyn Versioned>());
+    assert!(matches!(all, Err(nxs::Error::IncompatibleVersion { .. })));
+    let all = block_on(leak_root(Registry::new()).import_all::<dyn Versioned>());
+    assert!(all.unwrap().is_empty());
+
+    let required = InterfaceInfo::of::<dyn Versioned>();
+    let zero = InterfaceInfo { version: Version::new(0, 2, 1), ..required };
+    assert!(zero.is_compatible(&Version::new(0, 2, 5)));
+    assert!(!zero.is_compatible(&Version::new(0, 3, 0)));
+}
+
+#[test]
+fn multiple_providers() {
+    //! Of several providers of an interface, the one selected should be
+    //! imported, or else the one of highest priority that loads successfully,
+    //! and `import_all` should import every provider.
+
+    use nxs_interface::config::ConfigManager;
+
+    trait Named: LeafModule { fn name(&self) -> &'static str; }
+    impl Interface for dyn Named {
+        const VERSION: &'static str = "1.0.0";
+    }
+
+    macro_rules! named {
+        ($module:ident, $result:expr) => {
+            #[derive(DynCast, LeafModule)]
+            #[dyn_cast(base_traits(LeafModule, Named))]
+            #[leaf_module(provides(Named))]
+            struct $module;
+            impl Named for $module {
+                fn name(&self) -> &'static str { stringify!($module) }
+            }
+            impl $module {
+                async fn load(_root: &'static dyn RootModule)
+                -> nxs::Result<Self> {
+                    $result.map(|()| $module)
+                }
+            }
+        };
+    }
+    named!(FirstNamed, Ok(()));
+    named!(SecondNamed, Ok(()));
+    named!(BrokenNamed, Err(nxs::Error::module("BrokenNamed", "broken")));
+
+    let registry = || {
+        let mut registry = Registry::new();
+        registry.register::<FirstNamed>().register::<SecondNamed>();
+        registry
+    };
+    let import = |registry: Registry| {
+        let root = leak_root(registry);
+        block_on(root.import::<dyn Named>()).map(|h| h.get().unwrap().name())
+    };
+
+    // The latest registered provider is preferred, other things being equal:
+    let names = |registry: &Registry| -> Vec<_> {
+        registry.providers::<dyn Named>().iter().map(|m| m.name).collect()
+    };
+    assert_eq!(names(&registry()), ["SecondNamed", "FirstNamed"]);
+    assert_eq!(import(registry()).unwrap(), "SecondNamed");
+
+    let mut prioritised = registry();
+    prioritised.set_priority("FirstNamed", 1).unwrap();
+    assert_eq!(names(&prioritised), ["FirstNamed", "SecondNamed"]);
+    assert_eq!(import(prioritised).unwrap(), "FirstNamed");
+
+    let mut fallback = registry();
+    fallback.register::<BrokenNamed>();
+    fallback.set_priority("BrokenNamed", 1).unwrap();
+    assert_eq!(import(fallback).unwrap(), "SecondNamed");
+
+    let mut selected = registry();
+    selected.set_priority("SecondNamed", 1).unwrap();
+    selected.select("Named", "FirstNamed").unwrap();
+    assert_eq!(import(selected).unwrap(), "FirstNamed");
+
+    let mut selected_broken = registry();
+    selected_broken.register::<BrokenNamed>();
+    selected_broken.select("Named", "BrokenNamed").unwrap();
+    assert!(matches!(import(selected_broken), Err(nxs::Error::LoadFailed { .. })));
+
+    let mut invalid = registry();
+    assert!(matches!(invalid.select("Named", "Unknown"),
+                     Err(nxs::Error::UnknownModule { .. })));
+    assert!(matches!(invalid.set_priority("Unknown", 1),
+                     Err(nxs::Error::UnknownModule { .. })));
+    invalid.register::<StdConfig>();
+    assert!(invalid.select("Named", "StdConfig").is_err());
+
+    let mut all = registry();
+    all.select("Named", "FirstNamed").unwrap();
+    let root = leak_root(all);
+    let handles = block_on(root.import_all::<dyn Named>()).unwrap();
+    let names: Vec<_> = handles.iter().map(|h| h.get().unwrap().name()).collect();
+    assert_eq!(names, ["SecondNamed", "FirstNamed"]);
+    assert!(block_on(root.import_all::<dyn ConfigManager>()).unwrap().is_empty());
+}
+
+#[test]
+fn plugin() {
+    //! A plugin declaration should register its modules only if it was built
+    //! by the same `rustc` and against a compatible `nxs_interface`.
+
+    use nxs_interface::plugin::PluginDeclaration;
+
+    interface!(Plugged);
+
+    provider!(StdPlugged: Plugged);
+
+    nxs::export_plugin!(StdPlugged);
+
+    let with = |declaration: &PluginDeclaration| {
+        let mut registry = Registry::new();
+        let result = unsafe { registry.add_plugin(declaration) };
+        let names: Vec<_> = registry.manifests().map(|m| m.name).collect();
+        (result, names)
+    };
+    let with_versions = |rustc: &str, interface: &str| {
+        let (rustc, interface) = (format!("{}\0", rustc), format!("{}\0", interface));
+        with(&PluginDeclaration {
+            rustc_version: rustc.as_ptr() as _,
+            interface_version: interface.as_ptr() as _,
+            ..nxs_plugin_entry_v1
+        })
+    };
+
+    let (result, names) = with(&nxs_plugin_entry_v1);
+    assert!(result.is_ok());
+    assert_eq!(names, ["StdPlugged"]);
+
+    let (result, names) = with(&PluginDeclaration {
+        abi_version: 0, ..nxs_plugin_entry_v1
+    });
+    assert!(matches!(result, Err(PluginError::Abi { plugin: 0, .. })));
+    assert!(names.is_empty());
+
+    let (result, names) = with_versions("rustc 1.0.0", "0.1.0");
+    assert!(matches!(result, Err(PluginError::Rustc { .. })));
+    assert!(names.is_empty());
+
+    let rustc = nxs::plugin::RUSTC_VERSION;
+    let interface = nxs::plugin::INTERFACE_VERSION;
+    assert!(with_versions(rustc, interface).0.is_ok());
+    for version in &["0.0.1", "0.2.0", "1.0.0", "invalid"] {
+        let (result, names) = with_versions(rustc, version);
+        match result {
+            Err(PluginError::Interface { plugin, root }) => {
+                assert_eq!(plugin, *version);
+                assert_eq!(root, interface);
+            }
+            _ => panic!("expected `PluginError::Interface`"),
+        }
+        assert!(names.is_empty());
+    }
+}
+
+#[test]
+fn plugin_not_found() {
+    //! Loading a plugin from a file that is not a shared library should fail,
+    //! without affecting any other plugins in the same directory.
+
+    let dir = std::env::temp_dir().join(format!("nxs-plugins-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join(format!("invalid.{}", std::env::consts::DLL_EXTENSION));
+    std::fs::write(&file, "not a shared library").unwrap();
+    std::fs::write(dir.join("ignored.txt"), "not a plugin").unwrap();
+
+    let mut registry = Registry::new();
+    let results = unsafe { registry.load_plugins(&dir) }.unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].0, file);
+    assert!(matches!(results[0].1, Err(PluginError::Library(_))));
+    assert_eq!(registry.manifests().count(), 0);
+
+    let result = unsafe { registry.load_plugins(&dir) };
+    assert!(matches!(result, Err(PluginError::Io(_))));
+}
+
+#[test]
+fn reload() {
+    //! Reloading a module should shut down and invalidate the handles to it
+    //! and to modules that imported from it, and load each of those modules
+    //! again, leaving other modules untouched.
+
+    static BASE_LOADS: AtomicUsize = AtomicUsize::new(0);
+    static USER_LOADS: AtomicUsize = AtomicUsize::new(0);
+    static STOPPED: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+    interface!(Base);
+    interface!(User);
+    interface!(Lone);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Base))]
+    #[leaf_module(provides(Base), shutdown)]
+    struct StdBase;
+    impl Base for StdBase {}
+    impl StdBase {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            BASE_LOADS.fetch_add(1, Ordering::SeqCst);
+            Ok(StdBase)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            STOPPED.lock().unwrap().push("StdBase");
+            Ok(())
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, User))]
+    #[leaf_module(provides(User), requires(Base), shutdown)]
+    struct StdUser;
+    impl User for StdUser {}
+    impl StdUser {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            USER_LOADS.fetch_add(1, Ordering::SeqCst);
+            root.import::<dyn Base>().await?;
+            Ok(StdUser)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            STOPPED.lock().unwrap().push("StdUser");
+            Err(nxs::Error::module("StdUser", "still busy"))
+        }
+    }
+
+    provider!(StdLone: Lone);
+
+    let mut registry = Registry::new();
+    registry.register::<StdBase>().register::<StdUser>().register::<StdLone>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    let user = block_on(root.import::<dyn User>()).unwrap();
+    let base = block_on(root.import::<dyn Base>()).unwrap();
+    let lone = block_on(root.import::<dyn Lone>()).unwrap();
+
+    let reloaded = block_on(std_root.reload("StdBase")).unwrap();
+    assert_eq!(reloaded, ["StdUser", "StdBase"]);
+    assert_eq!(*STOPPED.lock().unwrap(), ["StdUser", "StdBase"]);
+    assert_eq!(BASE_LOADS.load(Ordering::SeqCst), 2);
+    assert_eq!(USER_LOADS.load(Ordering::SeqCst), 2);
+    assert!(!user.is_live() && !base.is_live() && lone.is_live());
+    match base.get() {
+        Err(nxs::Error::Unloaded { interface }) => {
+            assert_eq!(interface, TypeInfo::of::<dyn Base>());
+        }
+        _ => panic!("expected `Error::Unloaded`"),
+    }
+
+    // New imports should yield the reloaded instances, without loading again:
+    assert!(block_on(root.import::<dyn User>()).unwrap().is_live());
+    assert!(block_on(root.import::<dyn Base>()).unwrap().is_live());
+    assert_eq!(USER_LOADS.load(Ordering::SeqCst), 2);
+    assert_eq!(std_root.loaded().len(), 3);
+}
+
+#[test]
+fn unload_and_replace() {
+    //! Unloading a module should cause it to be loaded again on its next
+    //! import, and replacing it should cause the replacement to be loaded.
+
+    interface!(Greeter);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Greeter))]
+    #[leaf_module(name = "StdGreeter", version = "1.0.0", provides(Greeter))]
+    struct OldGreeter;
+    impl Greeter for OldGreeter {}
+    impl OldGreeter {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(OldGreeter)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Greeter))]
+    #[leaf_module(name = "StdGreeter", version = "2.0.0", provides(Greeter))]
+    struct NewGreeter;
+    impl Greeter for NewGreeter {}
+    impl NewGreeter {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(NewGreeter)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<OldGreeter>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    let version = |handle: &nxs::root::Handle<dyn Greeter>| {
+        handle.get().unwrap().dyn_manifest().version.to_string()
+    };
+
+    let old = block_on(root.import::<dyn Greeter>()).unwrap();
+    let unloaded = block_on(std_root.unload("StdGreeter")).unwrap();
+    assert_eq!(unloaded, ["StdGreeter"]);
+    assert!(block_on(std_root.unload("StdGreeter")).unwrap().is_empty());
+    assert!(!old.is_live());
+    match block_on(std_root.unload("NoGreeter")) {
+        Err(nxs::Error::UnknownModule { name }) => assert_eq!(name, "NoGreeter"),
+        _ => panic!("expected `Error::UnknownModule`"),
+    }
+
+    let old = block_on(root.import::<dyn Greeter>()).unwrap();
+    assert_eq!(version(&old), "1.0.0");
+    let replaced = block_on(std_root.replace(
+        "StdGreeter", NewGreeter::manifest(), NewGreeter::dyn_load,
+    )).unwrap();
+    assert_eq!(replaced, ["StdGreeter"]);
+    assert!(!old.is_live());
+    let new = block_on(root.import::<dyn Greeter>()).unwrap();
+    assert_eq!(version(&new), "2.0.0");
+}
+
+#[test]
+fn shutdown() {
+    //! Shutting down should stop each loaded module after the modules that
+    //! imported from it, and report modules that fail or time out.
+
+    static STOPPED: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+    interface!(Store);
+    interface!(Cache);
+    interface!(Stuck);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Store))]
+    #[leaf_module(provides(Store), shutdown)]
+    struct StdStore;
+    impl Store for StdStore {}
+    impl StdStore {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdStore)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            STOPPED.lock().unwrap().push("StdStore");
+            Ok(())
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Cache))]
+    #[leaf_module(provides(Cache), requires(Store, Stuck), shutdown)]
+    struct StdCache;
+    impl Cache for StdCache {}
+    impl StdCache {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Store>().await?;
+            root.import::<dyn Stuck>().await?;
+            Ok(StdCache)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            STOPPED.lock().unwrap().push("StdCache");
+            Err(nxs::Error::module("StdCache", "cache is dirty"))
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Stuck))]
+    #[leaf_module(provides(Stuck), shutdown)]
+    struct StdStuck;
+    impl Stuck for StdStuck {}
+    impl StdStuck {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdStuck)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            STOPPED.lock().unwrap().push("StdStuck");
+            futures::future::pending().await
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdStore>().register::<StdStuck>()
+        .register::<StdCache>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    let cache = block_on(root.import::<dyn Cache>()).unwrap();
+
+    let mut config = ShutdownConfig::default();
+    config.module_timeouts.insert("StdStuck".to_string(), Duration::from_millis(50));
+    let report = block_on(std_root.shutdown(&config));
+
+    let stopped = STOPPED.lock().unwrap().clone();
+    assert_eq!(stopped[0], "StdCache");
+    assert_eq!(stopped.len(), 3);
+    assert_eq!(stopped[2], "StdStore");
+    assert_eq!(report.stopped, ["StdStore"]);
+    assert!(!report.is_clean());
+    assert_eq!(report.failed.len(), 2);
+    for (name, failure) in &report.failed {
+        match (*name, failure) {
+            ("StdCache", ShutdownFailure::Error(error)) => {
+                assert_eq!(error.to_string(), "module `StdCache`: cache is dirty");
+            }
+            ("StdStuck", ShutdownFailure::TimedOut(timeout)) => {
+                assert_eq!(*timeout, Duration::from_millis(50));
+            }
+            _ => panic!("unexpected failure: {} {}", name, failure),
+        }
+    }
+    assert!(!cache.is_live());
+    assert!(std_root.loaded().is_empty());
+}
+
+#[test]
+fn config() {
+    //! The `[root]` table of a configuration file should be parsed and
+    //! validated, and the other tables kept as they are.
+
+    let path = std::path::Path::new("etc/nexus.toml");
+    let config = Config::parse(path, r#"
+        [root]
+        modules = ["nxs_std_text"]
+        plugins = "plugins"
+        shutdown_timeout = 1.5
+        module_shutdown_timeouts = { Commands = 3 }
+
+        [StdTextManager]
+        greeting = "hello"
+    "#).unwrap();
+    assert_eq!(config.root.modules, ["nxs_std_text"]);
+    assert_eq!(config.plugins_dir().unwrap(), path.with_file_name("plugins"));
+    let shutdown = config.shutdown();
+    assert_eq!(shutdown.timeout_for("StdTextManager"), Duration::from_millis(1500));
+    assert_eq!(shutdown.timeout_for("Commands"), Duration::from_secs(3));
+    assert_eq!(config.sections["StdTextManager"]["greeting"].as_str(), Some("hello"));
+
+    let empty = Config::parse(path, "").unwrap();
+    assert!(empty.root.modules.is_empty() && empty.plugins_dir().is_none());
+
+    for text in &[
+        "[root]\nshutdown_timeout = -1",
+        "[root]\nmodule_shutdown_timeouts = { Commands = nan }",
+        "[root]\nload_timeout = 1e30",
+        "[root.module_load_timeouts]\nCommands = 1e30",
+        "[root]\nunknown = true",
+        "[root]\nmodules = \"nxs_std_text\"",
+        "[root]\nlog_level = \"loud\"",
+        "[root.module_log_levels]\nCommands = \"quiet\"",
+        "[root.metrics]\naddress = \"localhost\"",
+        "[root.metrics]\ninterval = 0",
+        "[root.metrics]\ninterval = 1e30",
+    ] {
+        let error = Config::parse(path, text).err().unwrap();
+        assert!(error.starts_with("in [root]: "), "{}", error);
+    }
+}
+
+#[test]
+fn config_manager() {
+    //! Each module should read its own section of the configuration through
+    //! `ConfigManager`, chosen by the root according to the module's identity,
+    //! and be notified only of changes to that section.
+
+    use futures::{FutureExt, StreamExt};
+    use nxs_interface::config::ConfigManager;
+
+    trait Reader: LeafModule {
+        fn config(&self) -> &'static dyn ConfigManager;
+    }
+    impl Interface for dyn Reader {
+        const VERSION: &'static str = "1.0.0";
+    }
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Reader))]
+    #[leaf_module(provides(Reader), requires(ConfigManager))]
+    struct StdReader { config: nxs::root::Handle<dyn ConfigManager> }
+    impl Reader for StdReader {
+        fn config(&self) -> &'static dyn ConfigManager {
+            self.config.get().unwrap()
+        }
+    }
+    impl StdReader {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdReader { config: root.import().await? })
+        }
+    }
+
+    #[derive(serde::Deserialize, Debug, PartialEq)]
+    #[serde(deny_unknown_fields)]
+    struct Settings {
+        name: String,
+        #[serde(default)]
+        ports: Vec<u16>,
+    }
+
+    let dir = std::env::temp_dir().join(format!("nxs-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("nexus.toml");
+    let write = |text: &str| std::fs::write(&path, text).unwrap();
+    write("[Mine]\nname = \"a\"\n[Theirs]\nname = \"b\"\n");
+
+    let mut registry = Registry::new();
+    registry.register::<StdConfig>().register::<StdReader>();
+    registry.add_instance("Mine", "StdReader").unwrap();
+    registry.add_instance("Theirs", "StdReader").unwrap();
+    let config = Config::load(&path).unwrap();
+    let std_root: &'static StdRootModule
+        = Box::leak(Box::new(StdRootModule::with_config(registry, config)));
+    let root: &'static dyn RootModule = std_root;
+    let reader = |name| {
+        let reader = block_on(root.import_named::<dyn Reader>(name)).unwrap();
+        reader.get().unwrap().config()
+    };
+    let (mine, theirs) = (reader("Mine"), reader("Theirs"));
+    let settings = |manager: &dyn ConfigManager| manager.section::<Settings>();
+
+    assert_eq!((mine.module(), theirs.module()), ("Mine", "Theirs"));
+    assert_eq!(settings(mine).unwrap(), Settings { name: "a".into(), ports: vec![] });
+    let nobody = block_on(root.import::<dyn Reader>()).unwrap();
+    assert!(nobody.get().unwrap().config().raw_section().is_empty());
+    let own = block_on(root.import::<dyn ConfigManager>()).unwrap();
+    assert_eq!(own.get().unwrap().module(), "root");
+    assert!(own.get().unwrap().raw_section().is_empty());
+    let mut mine_changes = mine.changes();
+    let mut theirs_changes = theirs.changes();
+
+    write("[Mine]\nname = \"c\"\nports = [6667]\n[Theirs]\nname = \"b\"\n");
+    std_root.reload_config().unwrap();
+    assert_eq!(block_on(mine_changes.next()), Some(()));
+    assert!(theirs_changes.next().now_or_never().is_none());
+    assert_eq!(settings(mine).unwrap(), Settings { name: "c".into(), ports: vec![6667] });
+
+    // An invalid file should be rejected, keeping the previous configuration:
+    write("[Mine]\nname = ");
+    assert!(std_root.reload_config().is_err());
+    assert_eq!(settings(mine).unwrap().name, "c");
+
+    write("[Mine]\nname = \"d\"\nports = [6667, \"x\"]\n[Theirs]\n");
+    std_root.reload_config().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(block_on(theirs_changes.next()), Some(()));
+    assert_eq!(
+        settings(mine).err().unwrap().to_string(),
+        "invalid configuration for module `Mine` at `ports[1]`: invalid type: \
+         string \"x\", expected u16",
+    );
+    match settings(theirs) {
+        Err(nxs::Error::Config { module: "Theirs", key: None, message }) => {
+            assert_eq!(message, "missing field `name`");
+        }
+        other => panic!("expected `Error::Config`, got {:?}", other),
+    }
+}
+
+#[test]
+fn named_instances() {
+    //! Each named instance of a module should be loaded separately, reading
+    //! its own section of the configuration, and should be imported only by
+    //! name, leaving unnamed imports unchanged.
+
+    use nxs_interface::config::ConfigManager;
+
+    trait Network: LeafModule { fn server(&self) -> &str; }
+    impl Interface for dyn Network {
+        const VERSION: &'static str = "1.0.0";
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Network))]
+    #[leaf_module(provides(Network), requires(ConfigManager))]
+    struct StdNetwork { server: String }
+    impl Network for StdNetwork {
+        fn server(&self) -> &str { &self.server }
+    }
+    impl StdNetwork {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            let config = root.import::<dyn ConfigManager>().await?;
+            let section = config.get()?.raw_section();
+            let server = section.get("server").and_then(|s| s.as_str())
+                .unwrap_or("default").to_string();
+            Ok(StdNetwork { server })
+        }
+    }
+
+    let config = Config::parse("nexus.toml".as_ref(), "
+        [root.instances]
+        libera = \"StdNetwork\"
+        oftc = \"StdNetwork\"
+        [libera]
+        server = \"irc.libera.chat\"
+        [oftc]
+        server = \"irc.oftc.net\"
+    ").unwrap();
+    let mut registry = Registry::new();
+    registry.register::<StdConfig>().register::<StdNetwork>();
+    config.apply_to(&mut registry).unwrap();
+    assert!(registry.add_instance("oftc", "StdNetwork").is_err());
+    assert!(matches!(registry.add_instance("efnet", "NoNetwork"),
+                     Err(nxs::Error::UnknownModule { .. })));
+
+    let std_root: &'static StdRootModule
+        = Box::leak(Box::new(StdRootModule::with_config(registry, config)));
+    let root: &'static dyn RootModule = std_root;
+    let server = |handle: nxs::root::Handle<dyn Network>| {
+        handle.get().unwrap().server().to_string()
+    };
+
+    assert_eq!(server(block_on(root.import::<dyn Network>()).unwrap()), "default");
+    let libera = block_on(root.import_named::<dyn Network>("libera")).unwrap();
+    let oftc = block_on(root.import_named::<dyn Network>("oftc")).unwrap();
+    assert_eq!(server(libera.clone()), "irc.libera.chat");
+    assert_eq!(server(oftc.clone()), "irc.oftc.net");
+    let again = block_on(root.import_named::<dyn Network>("libera")).unwrap();
+    assert!(std::ptr::addr_eq(libera.get().unwrap(), again.get().unwrap()));
+    assert_eq!(block_on(root.import_all::<dyn Network>()).unwrap().len(), 3);
+
+    for name in &["efnet", "StdNetwork", "StdConfig"] {
+        match block_on(root.import_named::<dyn Network>(name)) {
+            Err(nxs::Error::NoInstance { interface, name: missing }) => {
+                assert_eq!(interface, TypeInfo::of::<dyn Network>());
+                assert_eq!(missing, *name);
+            }
+            _ => panic!("expected `Error::NoInstance`"),
+        }
+    }
+
+    // Each instance has its own lifecycle:
+    assert_eq!(block_on(std_root.unload("libera")).unwrap(), ["libera"]);
+    assert!(!libera.is_live() && oftc.is_live());
+    let mut loaded: Vec<_> = std_root.loaded().iter().map(|m| m.name).collect();
+    loaded.sort_unstable();
+    assert_eq!(loaded, ["StdConfig", "StdNetwork", "oftc"]);
+}
+
+#[test]
+fn try_import() {
+    //! An optional import should yield `None` if its interface is not
+    //! provided, but an error if its provider fails to load, and optional
+    //! interfaces should be ordered but not reported as missing.
+
+    interface!(Storage);
+    interface!(Absent);
+
+    provider!(StdStorage: Storage, requires(Absent));
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule))]
+    #[leaf_module(optional(Storage))]
+    struct StdUser { storage: Option<nxs::root::Handle<dyn Storage>> }
+    impl StdUser {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdUser { storage: root.try_import::<dyn Storage>().await? })
+        }
+    }
+
+    let manifest = StdUser::manifest();
+    assert!(manifest.requires.is_empty());
+    assert_eq!(manifest.optional, &[InterfaceInfo::of::<dyn Storage>()]);
+
+    let mut registry = Registry::new();
+    registry.register::<StdUser>();
+    assert!(registry.missing().is_empty());
+    let root = leak_root(registry);
+    assert!(block_on(StdUser::load(root)).unwrap().storage.is_none());
+    assert!(matches!(block_on(root.try_import::<dyn Absent>()), Ok(None)));
+
+    let mut registry = Registry::new();
+    registry.register::<StdUser>().register::<StdStorage>();
+    let order: Vec<_> = registry.load_order().unwrap().into_iter()
+        .map(|manifest| manifest.name).collect();
+    assert_eq!(order, &["StdStorage", "StdUser"]);
+    let root = leak_root(registry);
+    match block_on(StdUser::load(root)) {
+        Err(error) => match error.root_cause() {
+            nxs::Error::NotProvided { interface } => {
+                assert_eq!(*interface, TypeInfo::of::<dyn Absent>());
+            }
+            _ => panic!("expected `Error::NotProvided`, got {:?}", error),
+        },
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn panic_isolation() {
+    //! A panic while loading a module should fail that load, and any import
+    //! depending on it, without affecting other modules, and a panic while
+    //! shutting down should be reported as a failure.
+
+    static LOADS: AtomicUsize = AtomicUsize::new(0);
+
+    interface!(Volatile);
+    interface!(Dependent);
+    interface!(Stable);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Volatile))]
+    #[leaf_module(provides(Volatile))]
+    struct StdVolatile;
+    impl Volatile for StdVolatile {}
+    impl StdVolatile {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            LOADS.fetch_add(1, Ordering::SeqCst);
+            panic!("volatile module exploded");
+        }
+    }
+
+    provider!(StdDependent: Dependent, requires(Volatile));
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Stable))]
+    #[leaf_module(provides(Stable), shutdown)]
+    struct StdStable;
+    impl Stable for StdStable {}
+    impl StdStable {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdStable)
+        }
+        async fn shutdown(&'static self) -> nxs::Result<()> {
+            panic!("stable module failed to stop");
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdVolatile>().register::<StdDependent>()
+        .register::<StdStable>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+
+    for _ in 0..2 {
+        match block_on(root.import::<dyn Volatile>()) {
+            Err(nxs::Error::LoadFailed { module: "StdVolatile", source }) => {
+                match &*source {
+                    nxs::Error::Panicked { message } => {
+                        assert_eq!(message, "volatile module exploded");
+                    }
+                    other => panic!("expected `Error::Panicked`, got {:?}", other),
+                }
+            }
+            other => panic!("expected `Error::LoadFailed`, got {:?}", other.err()),
+        }
+    }
+    assert_eq!(LOADS.load(Ordering::SeqCst), 1);
+    let error = block_on(root.import::<dyn Dependent>()).err().unwrap();
+    assert!(matches!(error.root_cause(), nxs::Error::Panicked { .. }));
+    assert!(block_on(root.import::<dyn Stable>()).is_ok());
+
+    let report = block_on(std_root.shutdown(&ShutdownConfig::default()));
+    assert!(report.stopped.is_empty());
+    match &report.failed[..] {
+        [("StdStable", ShutdownFailure::Error(nxs::Error::Panicked { message }))] => {
+            assert_eq!(message, "stable module failed to stop");
+        }
+        other => panic!("unexpected failures: {:?}", other),
+    }
+}
+
+#[test]
+fn load_modules() {
+    //! Modules should be loaded concurrently, each within its configured time,
+    //! and the time taken by each, and spent waiting for each import, should
+    //! be reported.
+
+    use std::sync::atomic::AtomicBool;
+    use futures_timer::Delay;
+
+    static LEFT_STARTED: AtomicBool = AtomicBool::new(false);
+    static RIGHT_STARTED: AtomicBool = AtomicBool::new(false);
+
+    /// Waits until `flag` is set, which happens only if the other module is
+    /// loaded concurrently.
+    async fn wait_for(flag: &AtomicBool) {
+        while !flag.load(Ordering::SeqCst) {
+            Delay::new(Duration::from_millis(1)).await;
+        }
+    }
+
+    interface!(Left);
+    interface!(Right);
+    interface!(Slow);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Left))]
+    #[leaf_module(provides(Left))]
+    struct StdLeft;
+    impl Left for StdLeft {}
+    impl StdLeft {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            LEFT_STARTED.store(true, Ordering::SeqCst);
+            wait_for(&RIGHT_STARTED).await;
+            Ok(StdLeft)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Right))]
+    #[leaf_module(provides(Right))]
+    struct StdRight;
+    impl Right for StdRight {}
+    impl StdRight {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            RIGHT_STARTED.store(true, Ordering::SeqCst);
+            wait_for(&LEFT_STARTED).await;
+            Delay::new(Duration::from_millis(20)).await;
+            Ok(StdRight)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule))]
+    #[leaf_module(requires(Left, Right))]
+    struct StdBoth;
+    impl StdBoth {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Right>().await?;
+            root.import::<dyn Left>().await?;
+            Ok(StdBoth)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Slow))]
+    #[leaf_module(provides(Slow))]
+    struct StdSlow;
+    impl Slow for StdSlow {}
+    impl StdSlow {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            futures::future::pending().await
+        }
+    }
+
+    let config = Config::parse("nexus.toml".as_ref(), "
+        [root]
+        load_timeout = 5
+        [root.module_load_timeouts]
+        StdSlow = 0.05
+    ").unwrap();
+    assert_eq!(config.loading().timeout_for("StdLeft"), Duration::from_secs(5));
+    let mut registry = Registry::new();
+    registry.register::<StdBoth>().register::<StdLeft>()
+        .register::<StdRight>().register::<StdSlow>();
+    let root: &'static StdRootModule
+        = Box::leak(Box::new(StdRootModule::with_config(registry, config)));
+
+    block_on(root.load_modules(&["StdBoth", "StdLeft", "StdRight"])).unwrap();
+    let report = root.load_report();
+    let names: Vec<_> = report.modules.iter().map(|m| m.name).collect();
+    assert_eq!(names, ["StdBoth", "StdRight", "StdLeft"]);
+    let both = &report.modules[0];
+    assert!(!both.failed && both.elapsed.unwrap() >= Duration::from_millis(20));
+    let waits: Vec<_> = both.waits.iter()
+        .map(|wait| (wait.interface, wait.provider)).collect();
+    assert_eq!(waits, [(TypeInfo::of::<dyn Right>(), "StdRight")]);
+    assert!(both.waits[0].waited >= Duration::from_millis(20));
+    assert!(both.own_time().unwrap() < both.elapsed.unwrap());
+    assert!(report.total() >= both.elapsed.unwrap());
+
+    match block_on(root.load_modules(&["StdSlow"])) {
+        Err(nxs::Error::LoadFailed { module: "StdSlow", source }) => {
+            assert!(matches!(*source, nxs::Error::TimedOut { timeout }
+                             if timeout == Duration::from_millis(50)));
+        }
+        other => panic!("expected `Error::LoadFailed`, got {:?}", other),
+    }
+    let report = root.load_report();
+    assert!(report.modules.iter().any(|m| m.name == "StdSlow" && m.failed));
+    assert!(matches!(block_on(root.load_modules(&["Nobody"])),
+                     Err(nxs::Error::UnknownModule { .. })));
+}
+
+#[test]
+fn graph() {
+    //! The module graph should resolve each required interface to its
+    //! provider, and show missing providers, failed modules and the imports
+    //! actually made, in both DOT and JSON.
+
+    use crate::ModuleState;
+
+    interface!(Store);
+    interface!(Broken);
+    interface!(Absent);
+
+    provider!(StdStore: Store);
+    provider!(StdBroken: Broken, requires(Absent));
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule))]
+    #[leaf_module(requires(Store), optional(Broken))]
+    struct StdApp;
+    impl StdApp {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Store>().await?;
+            let _ = root.try_import::<dyn Broken>().await;
+            Ok(StdApp)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdStore>().register::<StdBroken>()
+        .register::<StdApp>();
+    let edges = |graph: &crate::ModuleGraph| -> Vec<_> {
+        graph.imports.iter().map(|import| {
+            (import.consumer, import.provider, import.imported)
+        }).collect()
+    };
+
+    let graph = registry.graph();
+    let names: Vec<_> = graph.modules.iter().map(|m| m.name).collect();
+    assert_eq!(names, ["StdStore", "StdBroken", "StdApp"]);
+    assert!(graph.modules.iter().all(|m| matches!(m.state, ModuleState::NotLoaded)));
+    assert_eq!(edges(&graph), [
+        ("StdBroken", None, false),
+        ("StdApp", Some("StdStore"), false),
+        ("StdApp", Some("StdBroken"), false),
+    ]);
+    assert!(graph.imports[0].is_missing() && graph.imports[2].optional);
+
+    let root = leak_std_root(registry);
+    block_on(root.load_modules(&["StdApp"])).unwrap();
+    let graph = root.graph();
+    assert!(matches!(graph.modules[0].state, ModuleState::Loaded));
+    assert!(matches!(graph.modules[1].state, ModuleState::Failed(_)));
+    assert_eq!(edges(&graph), [
+        ("StdBroken", None, false),
+        ("StdApp", Some("StdStore"), true),
+        ("StdApp", Some("StdBroken"), true),
+    ]);
+
+    let dot = graph.to_dot();
+    assert!(dot.starts_with("digraph modules {\n"));
+    assert!(dot.contains("\"StdApp\" -> \"StdStore\" [label=\"Store\", style=bold];"));
+    assert!(dot.contains("\"StdBroken\" -> \"no provider of Absent\" \
+                          [label=\"Absent\", color=red, fontcolor=red];"));
+    assert!(dot.contains("\"StdBroken\" [label=\"StdBroken"));
+    assert!(dot.contains("failed: module `StdBroken` failed to load"));
+
+    let json: serde_json::Value = serde_json::from_str(&graph.to_json()).unwrap();
+    assert_eq!(json["modules"][1]["state"], "failed");
+    assert!(json["modules"][0].get("error").is_none());
+    assert_eq!(json["imports"][0]["provider"], serde_json::Value::Null);
+    assert_eq!(json["imports"][0]["missing"], true);
+    assert_eq!(json["imports"][2]["optional"], true);
+}
+
+#[test]
+fn access_control() {
+    //! A restricted module should be able to import only the interfaces
+    //! declared in its manifest and those allowed for it, and any other
+    //! import should be denied.
+
+    interface!(Public);
+    interface!(Admin);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Public, Admin))]
+    #[leaf_module(provides(Public, Admin))]
+    struct StdServer;
+    impl Public for StdServer {}
+    impl Admin for StdServer {}
+    impl StdServer {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdServer)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule))]
+    #[leaf_module(requires(Public))]
+    struct StdSneaky;
+    impl StdSneaky {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Public>().await?;
+            root.import::<dyn Admin>().await?;
+            Ok(StdSneaky)
+        }
+    }
+
+    let registry = |restrict: bool, allowed: &[&str]| {
+        let mut registry = Registry::new();
+        registry.register::<StdServer>().register::<StdSneaky>();
+        if restrict { registry.restrict("StdSneaky", allowed).unwrap(); }
+        registry
+    };
+    let load = |registry| block_on(leak_std_root(registry).load_module("StdSneaky"));
+
+    assert!(load(registry(false, &[])).is_ok());
+    assert!(load(registry(true, &["Admin"])).is_ok());
+    match load(registry(true, &["Other"])).err().unwrap().root_cause() {
+        nxs::Error::AccessDenied { module, interface } => {
+            assert_eq!(*module, "StdSneaky");
+            assert_eq!(*interface, TypeInfo::of::<dyn Admin>());
+        }
+        other => panic!("expected `Error::AccessDenied`, got {:?}", other),
+    }
+
+    // The root itself is never restricted:
+    let root = leak_root(registry(true, &[]));
+    assert!(block_on(root.import::<dyn Admin>()).is_ok());
+
+    let config = Config::parse("nexus.toml".as_ref(), "
+        [root]
+        restrict_imports = true
+        [root.import_allow_lists]
+        StdSneaky = [\"dyn Admin\"]
+    ").unwrap();
+    let mut configured = registry(false, &[]);
+    config.apply_to(&mut configured).unwrap();
+    assert!(load(configured).is_ok());
+    let config = Config::parse("nexus.toml".as_ref(),
+                               "root.restrict_imports = true").unwrap();
+    let mut configured = registry(false, &[]);
+    config.apply_to(&mut configured).unwrap();
+    assert!(load(configured).is_err());
+
+    // A named instance should be restricted as its module is, whether the
+    // restriction is applied before or after the instance is added:
+    let config = Config::parse("nexus.toml".as_ref(), "
+        [root.instances]
+        sneaky2 = \"StdSneaky\"
+        [root.import_allow_lists]
+        StdSneaky = [\"Other\"]
+    ").unwrap();
+    let mut configured = registry(false, &[]);
+    config.apply_to(&mut configured).unwrap();
+    let mut restricted_first = registry(true, &["Other"]);
+    restricted_first.add_instance("sneaky2", "StdSneaky").unwrap();
+    for registry in [configured, restricted_first] {
+        let root = leak_std_root(registry);
+        match block_on(root.load_module("sneaky2")).unwrap_err().root_cause() {
+            nxs::Error::AccessDenied { module, interface } => {
+                assert_eq!(*module, "sneaky2");
+                assert_eq!(*interface, TypeInfo::of::<dyn Admin>());
+            }
+            other => panic!("expected `Error::AccessDenied`, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn scopes() {
+    //! A per-importer module should be loaded once for each module importing
+    //! it, knowing the name of that module, and a transient module should be
+    //! loaded again for every import, without being kept once loaded.
+    //! Unloading a module should unload all of its instances, and those loaded
+    //! on its behalf, and each module should be listed once while loaded.
+
+    use nxs::root::Handle;
+
+    trait Logger: LeafModule { fn owner(&self) -> Option<&'static str>; }
+    impl Interface for dyn Logger {
+        const VERSION: &'static str = "1.0.0";
+    }
+    trait Consumer: LeafModule { fn logger(&self) -> &Handle<dyn Logger>; }
+    impl Interface for dyn Consumer {
+        const VERSION: &'static str = "1.0.0";
+    }
+    interface!(Request);
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Logger))]
+    #[leaf_module(provides(Logger), scope = "per_importer")]
+    struct StdLogger { owner: Option<&'static str> }
+    impl Logger for StdLogger {
+        fn owner(&self) -> Option<&'static str> { self.owner }
+    }
+    impl StdLogger {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdLogger { owner: root.importer() })
+        }
+    }
+
+    static REQUESTS: AtomicUsize = AtomicUsize::new(0);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Request))]
+    #[leaf_module(provides(Request), scope = "transient")]
+    struct StdRequest;
+    impl Request for StdRequest {}
+    impl StdRequest {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            REQUESTS.fetch_add(1, Ordering::SeqCst);
+            Ok(StdRequest)
+        }
+    }
+
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Consumer))]
+    #[leaf_module(provides(Consumer), requires(Logger, Request))]
+    struct StdConsumer { logger: Handle<dyn Logger> }
+    impl Consumer for StdConsumer {
+        fn logger(&self) -> &Handle<dyn Logger> { &self.logger }
+    }
+    impl StdConsumer {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            root.import::<dyn Request>().await?;
+            root.import::<dyn Request>().await?;
+            Ok(StdConsumer { logger: root.import::<dyn Logger>().await? })
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdLogger>().register::<StdRequest>()
+        .register::<StdConsumer>();
+    registry.add_instance("OtherConsumer", "StdConsumer").unwrap();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+
+    let consumer = block_on(root.import::<dyn Consumer>()).unwrap();
+    let other = block_on(root.import_named::<dyn Consumer>("OtherConsumer"))
+        .unwrap();
+    let logger = block_on(root.import::<dyn Logger>()).unwrap();
+    let (consumer, other) = (consumer.get().unwrap(), other.get().unwrap());
+    assert_eq!(consumer.logger().get().unwrap().owner(), Some("StdConsumer"));
+    assert_eq!(other.logger().get().unwrap().owner(), Some("OtherConsumer"));
+    assert_eq!(logger.get().unwrap().owner(), None);
+    let again = block_on(root.import::<dyn Logger>()).unwrap();
+    assert!(std::ptr::addr_eq(logger.get().unwrap(), again.get().unwrap()));
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 4);
+    let tracked = std_root.modules.lock().unwrap().len();
+    for _ in 0..10 {
+        let request = block_on(root.import::<dyn Request>()).unwrap();
+        assert!(request.get().is_ok());
+    }
+    assert_eq!(REQUESTS.load(Ordering::SeqCst), 14);
+    assert_eq!(std_root.modules.lock().unwrap().len(), tracked);
+
+    let mut loaded: Vec<_> = std_root.loaded().into_iter()
+        .map(|manifest| manifest.name).collect();
+    loaded.sort_unstable();
+    assert_eq!(loaded, ["OtherConsumer", "StdConsumer", "StdLogger"]);
+
+    let unloaded = block_on(std_root.unload("OtherConsumer")).unwrap();
+    assert_eq!(unloaded, ["OtherConsumer"]);
+    assert!(other.logger().get().is_err());
+    assert!(consumer.logger().get().is_ok() && logger.get().is_ok());
+    assert_eq!(std_root.modules.lock().unwrap().len(), tracked - 2);
+
+    let unloaded = block_on(std_root.unload("StdLogger")).unwrap();
+    assert_eq!(unloaded, ["StdConsumer", "StdLogger"]);
+    assert!(logger.get().is_err() && consumer.logger().get().is_err());
+    assert!(std_root.loaded().is_empty());
+}
+
+#[test]
+fn event_bus() {
+    //! Events should be delivered to the subscribers to their own type and,
+    //! if published as castable, to the subscribers to each type to which
+    //! they can be cast, and the subscriptions of a module should end when
+    //! it is unloaded.
+
+    use futures::{FutureExt, StreamExt, stream::BoxStream};
+    use nxs::events::{EventBus, Event};
+    use nxs_std_events::StdEventBus;
+
+    trait ChatEvent: DynCast + Send + Sync { fn text(&self) -> &str; }
+    #[derive(DynCast)]
+    #[dyn_cast(base_traits(ChatEvent))]
+    struct Message(&'static str);
+    impl ChatEvent for Message {
+        fn text(&self) -> &str { self.0 }
+    }
+
+    type Events = Mutex<BoxStream<'static, Event<dyn ChatEvent>>>;
+    trait Listener: LeafModule { fn events(&self) -> &Events; }
+    impl Interface for dyn Listener {
+        const VERSION: &'static str = "1.0.0";
+    }
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Listener))]
+    #[leaf_module(provides(Listener), requires(EventBus))]
+    struct StdListener { events: Events }
+    impl Listener for StdListener {
+        fn events(&self) -> &Events { &self.events }
+    }
+    impl StdListener {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            let bus = root.import::<dyn EventBus>().await?;
+            let events = bus.get()?.subscribe_castable::<dyn ChatEvent>(root);
+            Ok(StdListener { events: Mutex::new(events) })
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdEventBus>().register::<StdListener>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    let bus = block_on(root.import::<dyn EventBus>()).unwrap();
+    let bus = bus.get().unwrap();
+
+    let mut numbers = bus.subscribe::<u32>(root);
+    let mut messages = bus.subscribe::<Message>(root);
+    let mut chat = bus.subscribe_castable::<dyn ChatEvent>(root);
+    bus.publish(1u32);
+    bus.publish(Message("plain"));
+    bus.publish_castable(Message("castable"));
+    bus.publish("ignored");
+    assert_eq!(*block_on(numbers.next()).unwrap(), 1);
+    assert!(numbers.next().now_or_never().is_none());
+    assert_eq!(block_on(messages.next()).unwrap().0, "plain");
+    assert_eq!(block_on(messages.next()).unwrap().0, "castable");
+    assert_eq!(block_on(chat.next()).unwrap().text(), "castable");
+    assert!(chat.next().now_or_never().is_none());
+
+    let listener = block_on(root.import::<dyn Listener>()).unwrap();
+    let events = listener.get().unwrap().events();
+    bus.publish_castable(Message("hello"));
+    let event = block_on(events.lock().unwrap().next()).unwrap();
+    assert_eq!(event.text(), "hello");
+    assert_eq!(event.as_dyn().type_id(), TypeId::of::<Message>());
+
+    // The stream should end even if no event is published after unloading:
+    let waiting = std::thread::spawn(move || {
+        block_on(events.lock().unwrap().next()).is_none()
+    });
+    std::thread::sleep(Duration::from_millis(50));
+    block_on(std_root.unload("StdListener")).unwrap();
+    assert!(waiting.join().unwrap());
+    bus.publish_castable(Message("goodbye"));
+    assert_eq!(block_on(chat.next()).unwrap().text(), "hello");
+    assert_eq!(block_on(chat.next()).unwrap().text(), "goodbye");
+}
+
+#[test]
+fn logging() {
+    //! Each module's log messages should be tagged with its name and version
+    //! and written with their fields to the configured sinks, at levels that
+    //! may be changed while running, and the `log` and `tracing` facades
+    //! should be routed to the same sinks.
+
+    use nxs::logging::Level;
+    use serde_json::Value;
+
+    trait Chatty: LeafModule { fn chat(&self, topic: &str); }
+    impl Interface for dyn Chatty {
+        const VERSION: &'static str = "1.0.0";
+    }
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Chatty))]
+    #[leaf_module(provides(Chatty), version = "2.1.0")]
+    struct StdChatty { root: &'static dyn RootModule }
+    impl Chatty for StdChatty {
+        fn chat(&self, topic: &str) {
+            nxs::nxs_info!(self.root.logger(), { topic: topic, count: 3 },
+                           "chatting about {}", topic);
+            nxs::nxs_debug!(self.root.logger(), "details");
+        }
+    }
+    impl StdChatty {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdChatty { root })
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("nxs-logs-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let config = Config::parse(&dir.join("nexus.toml"), "
+        [root]
+        log_level = \"warn\"
+        [root.module_log_levels]
+        StdChatty = \"Info\"
+        third_party = \"debug\"
+        [[root.log_sinks]]
+        kind = \"file\"
+        path = \"nexus.log\"
+        format = \"json\"
+        max_bytes = 500
+        keep = 1
+    ").unwrap();
+    let mut registry = Registry::new();
+    registry.register::<StdChatty>();
+    let std_root: &'static StdRootModule
+        = Box::leak(Box::new(StdRootModule::with_config(registry, config)));
+    let root: &'static dyn RootModule = std_root;
+    assert!(std_root.bridge_log_facades());
+    let read = |name: &str| -> Vec<Value> {
+        std::fs::read_to_string(dir.join(name)).unwrap_or_default().lines()
+            .map(|line| serde_json::from_str(line).unwrap()).collect()
+    };
+
+    let chatty = block_on(root.import::<dyn Chatty>()).unwrap();
+    chatty.get().unwrap().chat("weather");
+    let lines = read("nexus.log");
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0]["module"], "StdChatty");
+    assert_eq!(lines[0]["version"], "2.1.0");
+    assert_eq!(lines[0]["level"], "info");
+    assert_eq!(lines[0]["message"], "chatting about weather");
+    assert_eq!(lines[0]["fields"]["topic"], "weather");
+    assert_eq!(lines[0]["fields"]["count"], 3);
+
+    std_root.set_log_level("StdChatty", Some(Level::Debug));
+    chatty.get().unwrap().chat("news");
+    assert_eq!(std_root.log_level("StdChatty"), Level::Debug);
+    std_root.set_log_level("StdChatty", None);
+    chatty.get().unwrap().chat("sport");
+    assert_eq!(std_root.log_level("StdChatty"), Level::Warn);
+
+    log::debug!(target: "third_party::client", "from log");
+    log::trace!(target: "third_party::client", "too verbose");
+    tracing::debug!(target: "third_party", answer = 42, "from tracing");
+    tracing::info!(target: "other", "ignored");
+
+    // The file should have been rotated once it exceeded 500 bytes:
+    let mut lines = read("nexus.log.1");
+    assert!(!lines.is_empty());
+    lines.extend(read("nexus.log"));
+    let messages: Vec<_> = lines.iter()
+        .map(|line| line["message"].as_str().unwrap()).collect();
+    assert_eq!(messages, ["chatting about weather", "chatting about news",
+                          "details", "from log", "from tracing"]);
+    assert_eq!(lines[3]["module"], "third_party::client");
+    assert_eq!(lines[3]["version"], Value::Null);
+    assert_eq!(lines[4]["fields"]["answer"], "42");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn metrics() {
+    //! Each module's metrics should be prefixed with its name, exposed in the
+    //! Prometheus text format together with the root's own, and served over
+    //! HTTP. A module should not be able to register a metric under the
+    //! root's prefix, or one of another module whose name has the same prefix.
+
+    use std::io::{Read, Write};
+    use nxs::metrics::{Metrics, Counter, Histogram};
+    use crate::StdMetrics;
+
+    interface!(Broken);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Broken))]
+    #[leaf_module(provides(Broken))]
+    struct StdBroken;
+    impl Broken for StdBroken {}
+    impl StdBroken {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Err(nxs::Error::module("StdBroken", "broken"))
+        }
+    }
+
+    trait Handler: LeafModule { fn handle(&self, seconds: f64); }
+    impl Interface for dyn Handler {
+        const VERSION: &'static str = "1.0.0";
+    }
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Handler))]
+    #[leaf_module(provides(Handler), requires(Metrics))]
+    struct StdHandler { handled: Counter, latency: Histogram }
+    impl Handler for StdHandler {
+        fn handle(&self, seconds: f64) {
+            self.handled.inc();
+            self.latency.observe(seconds);
+        }
+    }
+    impl StdHandler {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            let metrics = root.import::<dyn Metrics>().await?;
+            let metrics = metrics.get()?;
+            metrics.gauge("queue_length", "The length of the queue.")?.set(2.5);
+            let handled = metrics.counter("handled_total", "Handled.")?;
+            assert!(metrics.gauge("handled_total", "").is_err());
+            assert!(metrics.counter("bad-name", "").is_err());
+            Ok(StdHandler {
+                handled,
+                latency: metrics.histogram("latency_seconds", "Latency.",
+                                           &[1.0, 0.1, f64::NAN])?,
+            })
+        }
+    }
+
+    interface!(Clash);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Clash))]
+    #[leaf_module(provides(Clash), requires(Metrics))]
+    struct StdClash;
+    impl Clash for StdClash {}
+    impl StdClash {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            let metrics = root.import::<dyn Metrics>().await?;
+            let metrics = metrics.get()?;
+            match root.instance() {
+                Some("Nxs") => {
+                    assert!(metrics.counter("module_load_seconds", "")
+                                .is_err());
+                    assert!(metrics.gauge("modules_loaded", "").is_err());
+                }
+                _ => {
+                    assert!(metrics.counter("handled_total", "").is_err());
+                    metrics.counter("clashes_total", "Clashes.")?.inc();
+                }
+            }
+            Ok(StdClash)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdMetrics>();
+    registry.register::<StdBroken>();
+    registry.register::<StdHandler>();
+    registry.register::<StdClash>();
+    registry.add_instance("Nxs", "StdClash").unwrap();
+    registry.add_instance("std_handler", "StdClash").unwrap();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    assert!(block_on(root.import::<dyn Broken>()).is_err());
+    let handler = block_on(root.import::<dyn Handler>()).unwrap();
+    for seconds in [0.05, 0.5, 5.0] {
+        handler.get().unwrap().handle(seconds);
+    }
+    for name in ["Nxs", "std_handler"] {
+        block_on(root.import_named::<dyn Clash>(name)).unwrap();
+    }
+
+    let text = std_root.render_metrics();
+    for line in [
+        "# TYPE std_handler_handled_total counter",
+        "std_handler_handled_total 3",
+        "std_handler_queue_length 2.5",
+        "# TYPE std_handler_latency_seconds histogram",
+        "std_handler_latency_seconds_bucket{le=\"0.1\"} 1",
+        "std_handler_latency_seconds_bucket{le=\"1\"} 2",
+        "std_handler_latency_seconds_bucket{le=\"+Inf\"} 3",
+        "std_handler_latency_seconds_sum 5.55",
+        "std_handler_latency_seconds_count 3",
+        "nxs_imports_total{interface=\"Handler\",provider=\"StdHandler\"} 1",
+        "nxs_imports_total{interface=\"Metrics\",provider=\"StdMetrics\"} 3",
+        "nxs_import_failures_total{interface=\"Broken\",\
+                                   provider=\"StdBroken\"} 1",
+        "nxs_module_load_failures_total{module=\"StdBroken\"} 1",
+        "std_handler_clashes_total 1",
+        "nxs_modules_loaded 4",
+    ] {
+        assert!(text.lines().any(|l| l == line), "{:?} in:\n{}", line, text);
+    }
+    assert!(text.contains("nxs_module_load_seconds{module=\"StdHandler\"} "));
+
+    let address = std_root.serve_metrics(([127, 0, 0, 1], 0).into()).unwrap();
+    let get = |path: &str| {
+        let mut stream = std::net::TcpStream::connect(address).unwrap();
+        write!(stream, "GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path)
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    };
+    let response = get("/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("\r\n\r\n# HELP "));
+    assert!(response.contains("std_handler_handled_total 3\n"));
+    assert!(get("/other").starts_with("HTTP/1.1 404 "));
+}
+
+#[test]
+fn health() {
+    //! Modules implementing `Health` should be asked for their health, and
+    //! the root should report the worst of each module's instances, treat
+    //! other loaded modules as healthy, and isolate panicking checks.
+
+    use std::sync::atomic::AtomicBool;
+    use nxs::health::{Health, HealthState, HealthStatus};
+
+    static CONNECTED: AtomicBool = AtomicBool::new(true);
+
+    interface!(Backend);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Backend, Health))]
+    #[leaf_module(provides(Backend), scope = "per_importer")]
+    struct StdBackend { root: &'static dyn RootModule }
+    impl Backend for StdBackend {}
+    impl Health for StdBackend {
+        fn health(&self) -> HealthStatus {
+            match (CONNECTED.load(Ordering::SeqCst), self.root.importer()) {
+                (true, _) => HealthStatus::healthy("connected"),
+                (false, Some("StdFrontend")) => {
+                    HealthStatus::degraded("reconnecting")
+                }
+                (false, _) => HealthStatus::failed("disconnected"),
+            }
+        }
+    }
+    impl StdBackend {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdBackend { root })
+        }
+    }
+
+    interface!(Frontend);
+    provider!(StdFrontend: Frontend, requires(Backend));
+
+    interface!(Flaky);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Flaky, Health))]
+    #[leaf_module(provides(Flaky))]
+    struct StdFlaky;
+    impl Flaky for StdFlaky {}
+    impl Health for StdFlaky {
+        fn health(&self) -> HealthStatus { panic!("cannot tell") }
+    }
+    impl StdFlaky {
+        async fn load(_root: &'static dyn RootModule) -> nxs::Result<Self> {
+            Ok(StdFlaky)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdBackend>();
+    registry.register::<StdFrontend>();
+    registry.register::<StdFlaky>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    assert!(std_root.status().modules.is_empty());
+    block_on(root.import::<dyn Frontend>()).unwrap();
+
+    let status = std_root.status();
+    assert!(status.is_healthy());
+    let states: Vec<_> = status.modules.iter()
+        .map(|m| (m.name, m.health.state, m.health.message.as_str()))
+        .collect();
+    assert_eq!(states, [
+        ("StdBackend", HealthState::Healthy, "connected"),
+        ("StdFrontend", HealthState::Healthy, ""),
+    ]);
+
+    // The root's own instance of the backend fails, while the frontend's
+    // is only degraded:
+    block_on(root.import::<dyn Backend>()).unwrap();
+    CONNECTED.store(false, Ordering::SeqCst);
+    let status = std_root.status();
+    assert_eq!(status.overall(), HealthState::Failed);
+    assert_eq!(status.modules[0].health, HealthStatus::failed("disconnected"));
+    assert_eq!(status.to_string().lines().next().unwrap(),
+               "failed: 2 modules, 0 degraded, 1 failed");
+    block_on(std_root.unload("StdBackend")).unwrap();
+    block_on(root.import::<dyn Frontend>()).unwrap();
+    let status = std_root.status();
+    assert_eq!(status.overall(), HealthState::Degraded);
+    assert_eq!(status.modules[0].to_string(),
+               "module `StdBackend` 0.1.0 is degraded: reconnecting");
+
+    block_on(root.import::<dyn Flaky>()).unwrap();
+    let status = std_root.status();
+    assert_eq!(status.modules[2].name, "StdFlaky");
+    assert_eq!(status.modules[2].health.state, HealthState::Failed);
+    assert!(status.modules[2].health.message.contains("cannot tell"));
+}
+
+#[test]
+fn tasks() {
+    //! A module's background tasks should run on the root's executor until
+    //! they finish or the module is unloaded, and a panic in one should be
+    //! reported against that module.
+
+    use nxs::task::TaskHandle;
+
+    static SPAWNED: AtomicUsize = AtomicUsize::new(0);
+    static HANDLES: Mutex<Vec<TaskHandle>> = Mutex::new(Vec::new());
+
+    interface!(Connection);
+    #[derive(DynCast, LeafModule)]
+    #[dyn_cast(base_traits(LeafModule, Connection))]
+    #[leaf_module(provides(Connection))]
+    struct StdConnection;
+    impl Connection for StdConnection {}
+    impl StdConnection {
+        async fn load(root: &'static dyn RootModule) -> nxs::Result<Self> {
+            let spawner = root.spawner();
+            let mut handles = HANDLES.lock().unwrap();
+            handles.push(spawner.spawn("done", async {})?);
+            handles.push(spawner.spawn("reader", futures::future::pending())?);
+            handles.push(spawner.spawn("writer", async { panic!("lost") })?);
+            Ok(StdConnection)
+        }
+    }
+
+    let mut registry = Registry::new();
+    registry.register::<StdConnection>();
+    let std_root = leak_std_root(registry);
+    let root: &'static dyn RootModule = std_root;
+    std_root.set_executor(|task| {
+        SPAWNED.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(move || block_on(task));
+    });
+    assert!(matches!(root.spawner().spawn("root", async {}),
+                     Err(nxs::Error::Unsupported { .. })));
+
+    block_on(root.import::<dyn Connection>()).unwrap();
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 3);
+    let handles = HANDLES.lock().unwrap().clone();
+    assert!(block_on(handles[0].join()).is_ok());
+    match block_on(handles[2].join()) {
+        Err(nxs::Error::Panicked { message }) => assert_eq!(message, "lost"),
+        result => panic!("unexpected outcome: {:?}", result),
+    }
+    assert!(handles[2].is_finished() && !handles[1].is_finished());
+    let text = std_root.render_metrics();
+    assert!(text.contains("nxs_task_panics_total{module=\"StdConnection\"} 1"));
+
+    // Unloading the module should cancel its remaining task:
+    block_on(std_root.unload("StdConnection")).unwrap();
+    assert!(matches!(block_on(handles[1].join()),
+                     Err(nxs::Error::Cancelled)));
+
+    // A task cancelled through its handle should stop before it finishes:
+    block_on(root.import::<dyn Connection>()).unwrap();
+    let reader = HANDLES.lock().unwrap()[4].clone();
+    reader.cancel();
+    assert!(matches!(block_on(reader.join()), Err(nxs::Error::Cancelled)));
+    assert_eq!(SPAWNED.load(Ordering::SeqCst), 6);
+}
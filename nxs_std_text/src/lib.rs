@@ -6,7 +6,9 @@ use nxs_interface::{
 };
 
 #[derive(DynCast, LeafModule)]
-struct StdTextManager {
+#[dyn_cast(base_traits(LeafModule, TextManager))]
+#[leaf_module(provides(TextManager))]
+pub struct StdTextManager {
     root: &'static dyn RootModule,
 }
 
@@ -18,5 +20,14 @@ impl StdTextManager {
     }
 }
 
+/// Each message sent is written to the log of the root, as this manager is
+/// not connected to any chat network.
 impl TextManager for StdTextManager {
+    fn send(&self, target: &str, text: &str) -> nxs::Result<()> {
+        nxs::nxs_info!(self.root.logger(), { target: target }, "{}", text);
+        Ok(())
+    }
 }
+
+#[cfg(feature = "plugin")]
+nxs::export_plugin!(StdTextManager);